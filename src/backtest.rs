@@ -0,0 +1,254 @@
+//! Replaying detection over a time series of price snapshots.
+//!
+//! Snapshots come from a directory of price files, taken in file name order
+//! with each file's stem as its timestamp, or from an NDJSON file with one
+//! snapshot per line:
+//!
+//! ```json
+//! {"timestamp": "2024-05-01T12:00:00Z", "prices": [{"symbol": "ETHBTC", "price": "0.05759"}]}
+//! ```
+
+use crate::detect::canonical_cycle;
+use crate::detect::enumerate::enumerate_negative_cycles;
+use crate::exact::retain_exact;
+use crate::exchange_info::RulesMap;
+use crate::fees::FeeSchedule;
+use crate::graph::{GraphBuilder, ParallelEdges};
+use crate::ingest::{self, parse_entries, EntryKey, IngestError, MalformedEntry, PriceFormat, Quote, TickerMapping};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Prices observed at one point in time.
+pub struct Snapshot {
+    pub timestamp: String,
+    pub quotes: Vec<Quote>,
+}
+
+impl Snapshot {
+    /// Stamps every quote with `timestamp`, so the edges built from them carry it.
+    pub fn new(timestamp: String, quotes: Vec<Quote>) -> Snapshot {
+        let quotes = quotes.into_iter().map(|quote| Quote { timestamp: Some(timestamp.clone()), ..quote }).collect();
+        Snapshot { timestamp, quotes }
+    }
+}
+
+/// One line of an NDJSON snapshot stream.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SnapshotLine {
+    /// A string or a number such as epoch milliseconds.
+    timestamp: Value,
+    prices: Vec<Value>,
+}
+
+/// Reads snapshots from a directory of price files or an NDJSON stream.
+/// Fails with an `IngestError` listing every malformed line or entry.
+pub fn load_snapshots(path: &Path, format: PriceFormat) -> Result<Vec<Snapshot>, Box<dyn Error>> {
+    if path.is_dir() {
+        let mut files: Vec<PathBuf> = fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<_, _>>()?;
+        files.retain(|f| f.is_file());
+        files.sort();
+        return files.into_iter().map(|file| snapshot_from_file(&file, format)).collect();
+    }
+
+    let file = path.display().to_string();
+    let data = fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", file, e))?;
+    let mut snapshots = Vec::new();
+    let mut errors = Vec::new();
+    for (i, line) in data.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let line_file = format!("{} line {}", file, i + 1);
+        match serde_json::from_str::<SnapshotLine>(line) {
+            Ok(snapshot) => {
+                let quotes = parse_entries(&line_file, snapshot.prices, format, &mut errors);
+                let timestamp = match snapshot.timestamp {
+                    Value::String(s) => s,
+                    other => other.to_string(),
+                };
+                snapshots.push(Snapshot::new(timestamp, quotes));
+            }
+            Err(e) => errors.push(MalformedEntry {
+                file: line_file,
+                key: EntryKey::Name("snapshot".to_string()),
+                reason: e.to_string(),
+            }),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(Box::new(IngestError { entries: errors }));
+    }
+    Ok(snapshots)
+}
+
+/// Wraps a price file as a snapshot stamped with its file stem.
+pub fn snapshot_from_file(path: &Path, format: PriceFormat) -> Result<Snapshot, Box<dyn Error>> {
+    let timestamp = path.file_stem().unwrap_or(path.as_os_str()).to_string_lossy().into_owned();
+    let quotes = ingest::prices(&[path.to_path_buf()], format)?;
+    Ok(Snapshot::new(timestamp, quotes))
+}
+
+/// Opportunities seen in one snapshot.
+pub struct TickSummary {
+    pub timestamp: String,
+    /// Negative cycles enumerated in this snapshot.
+    pub cycles: usize,
+    /// Product of rates of the best cycle, 1 when there is none.
+    pub best_return: f64,
+    pub best_cycle: Vec<String>,
+    /// Consecutive ticks, including this one, in which the best cycle has been open.
+    pub best_persistence: usize,
+    /// Longest open streak among this snapshot's cycles.
+    pub max_persistence: usize,
+}
+
+/// A cycle that stayed profitable over consecutive ticks.
+pub struct OpportunityWindow {
+    pub cycle: Vec<String>,
+    pub opened: String,
+    /// Last tick the cycle was seen in.
+    pub closed: String,
+    pub ticks: usize,
+    pub best_return: f64,
+}
+
+/// How every snapshot's graph is built and which of its cycles count.
+pub struct BacktestConfig {
+    pub policy: ParallelEdges,
+    /// Lot size rules by symbol, bounding the edges of every snapshot.
+    pub rules: RulesMap,
+    /// Longest cycle, in legs, to enumerate.
+    pub max_len: usize,
+    /// Cycles must return more than `1 + epsilon` exactly.
+    pub epsilon: f64,
+    /// Minimum return per leg, in basis points.
+    pub min_return_bps: f64,
+}
+
+/// Rebuilds the graph for every snapshot, enumerates the negative cycles
+/// `config` accepts, and tracks how long each one stays open. Windows still
+/// open after the last snapshot are closed there.
+pub fn backtest(
+    ticker_mapping: &TickerMapping,
+    snapshots: Vec<Snapshot>,
+    fees: &FeeSchedule,
+    config: BacktestConfig,
+) -> (Vec<TickSummary>, Vec<OpportunityWindow>) {
+    let BacktestConfig { policy, mut rules, max_len, epsilon, min_return_bps } = config;
+    let mut ticks = Vec::new();
+    let mut windows = Vec::new();
+    let mut open: HashMap<Vec<String>, OpportunityWindow> = HashMap::new();
+
+    for snapshot in snapshots {
+        println!("Snapshot {}: {} quotes", snapshot.timestamp, snapshot.quotes.len());
+        let mut builder = GraphBuilder::with_rules(policy, rules);
+        builder.add_quotes(ticker_mapping, snapshot.quotes, fees, None);
+        rules = std::mem::take(&mut builder.rules);
+        let (graph, _, _) = builder.finish();
+        let mut cycles = enumerate_negative_cycles(&graph, max_len);
+        cycles.retain(|c| c.clears_threshold(&graph, min_return_bps));
+        retain_exact(&graph, &mut cycles, epsilon, |c| &c.edges);
+
+        let mut still_open = HashMap::new();
+        let mut summary = TickSummary {
+            timestamp: snapshot.timestamp.clone(),
+            cycles: cycles.len(),
+            best_return: 1.0,
+            best_cycle: Vec::new(),
+            best_persistence: 0,
+            max_persistence: 0,
+        };
+        for cycle in cycles {
+            // Node indices change between rebuilds, names do not.
+            let key = canonical_cycle(&cycle.currencies);
+            // The same loop can show up twice through parallel symbols.
+            let mut window = match still_open.remove(&key) {
+                Some(window) => window,
+                None => {
+                    let mut window = open.remove(&key).unwrap_or_else(|| OpportunityWindow {
+                        cycle: key.clone(),
+                        opened: snapshot.timestamp.clone(),
+                        closed: String::new(),
+                        ticks: 0,
+                        best_return: 1.0,
+                    });
+                    window.closed = snapshot.timestamp.clone();
+                    window.ticks += 1;
+                    window
+                }
+            };
+            window.best_return = window.best_return.max(cycle.rate_product);
+            summary.max_persistence = summary.max_persistence.max(window.ticks);
+            if cycle.rate_product > summary.best_return {
+                summary.best_return = cycle.rate_product;
+                summary.best_cycle = cycle.currencies;
+                summary.best_persistence = window.ticks;
+            }
+            still_open.insert(key, window);
+        }
+        // Cycles that did not reappear closed at their last sighting.
+        windows.extend(open.into_values());
+        open = still_open;
+        ticks.push(summary);
+    }
+    windows.extend(open.into_values());
+    windows.sort_by(|a, b| b.ticks.cmp(&a.ticks).then(b.best_return.total_cmp(&a.best_return)));
+    (ticks, windows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::fixtures::{mapping, quote};
+
+    fn snapshot(timestamp: &str, prices: &[(&str, &str)]) -> Snapshot {
+        let quotes = prices.iter().map(|&(symbol, price)| quote(symbol, price, price)).collect();
+        Snapshot::new(timestamp.to_string(), quotes)
+    }
+
+    #[test]
+    fn windows_open_close_and_reopen() {
+        let mapping = mapping(&[
+            ("ETHBTC", "ETH", "BTC"),
+            ("ETHBTC2", "ETH", "BTC"),
+            ("BTCUSDT", "BTC", "USDT"),
+            ("ETHUSDT", "ETH", "USDT"),
+        ]);
+        // ETH -> BTC -> USDT is worth 3000 USDT, so ETH below that in USDT is a cycle.
+        let snapshots = vec![
+            snapshot("t1", &[("ETHBTC", "0.05"), ("BTCUSDT", "60000"), ("ETHUSDT", "2900")]),
+            // A second symbol on ETH/BTC gives the same loop twice in one tick.
+            snapshot("t2", &[("ETHBTC", "0.05"), ("ETHBTC2", "0.05"), ("BTCUSDT", "60000"), ("ETHUSDT", "2900")]),
+            snapshot("t3", &[("ETHBTC", "0.05"), ("BTCUSDT", "60000"), ("ETHUSDT", "3000")]),
+            snapshot("t4", &[("ETHBTC", "0.05"), ("BTCUSDT", "60000"), ("ETHUSDT", "2950")]),
+        ];
+        let config = BacktestConfig {
+            policy: ParallelEdges::KeepAll,
+            rules: RulesMap::new(),
+            max_len: 3,
+            epsilon: 0.0,
+            min_return_bps: 0.0,
+        };
+        let (ticks, windows) = backtest(&mapping, snapshots, &FeeSchedule::default(), config);
+
+        let rows: Vec<_> = ticks
+            .iter()
+            .map(|t| (t.timestamp.as_str(), t.cycles, t.best_persistence, t.max_persistence))
+            .collect();
+        assert_eq!(rows, [("t1", 1, 1, 1), ("t2", 2, 2, 2), ("t3", 0, 0, 0), ("t4", 1, 1, 1)]);
+        assert!((ticks[0].best_return - 3000.0 / 2900.0).abs() < 1e-12);
+        assert_eq!(canonical_cycle(&ticks[1].best_cycle), ["BTC", "USDT", "ETH"]);
+        assert_eq!(ticks[2].best_return, 1.0);
+        assert!(ticks[2].best_cycle.is_empty());
+
+        let spans: Vec<_> = windows.iter().map(|w| (w.opened.as_str(), w.closed.as_str(), w.ticks)).collect();
+        assert_eq!(spans, [("t1", "t2", 2), ("t4", "t4", 1)]);
+        assert!(windows.iter().all(|w| w.cycle == ["BTC", "USDT", "ETH"]));
+        assert!((windows[0].best_return - 3000.0 / 2900.0).abs() < 1e-12);
+        assert!((windows[1].best_return - 3000.0 / 2950.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,239 @@
+//! L2 order book depth and the executable size of a cycle.
+//!
+//! Snapshots are a JSON array of Binance `depth`-style books tagged with their
+//! symbol, levels best first:
+//!
+//! ```json
+//! [{ "symbol": "ETHBTC", "bids": [["0.05759", "12.5"]], "asks": [["0.05761", "3.1"]] }]
+//! ```
+
+use crate::edge::{Edge, Side};
+use crate::ingest::{EntryKey, IngestError, MalformedEntry};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Fraction of an order allowed to stay unfilled without calling the book exhausted.
+const FILL_TOLERANCE: f64 = 1e-12;
+
+/// One symbol's order book as found in the snapshot file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DepthSnapshot {
+    pub symbol: String,
+    #[serde(rename = "lastUpdateId", default)]
+    pub last_update_id: Option<u64>,
+    /// `[price, quantity]` pairs as decimal strings.
+    pub bids: Vec<[String; 2]>,
+    pub asks: Vec<[String; 2]>,
+}
+
+/// Parsed price levels as `(price, base quantity)`, best first.
+#[derive(Clone, Debug, Default)]
+pub struct OrderBook {
+    pub bids: Vec<(f64, f64)>,
+    pub asks: Vec<(f64, f64)>,
+}
+
+pub type OrderBooks = HashMap<String, OrderBook>;
+
+/// Parses one side of a book, rejecting non-positive or non-finite levels.
+fn parse_levels(levels: &[[String; 2]]) -> Result<Vec<(f64, f64)>, String> {
+    levels
+        .iter()
+        .map(|[price, qty]| match (price.parse::<f64>(), qty.parse::<f64>()) {
+            (Ok(p), Ok(q)) if p.is_finite() && q.is_finite() && p > 0.0 && q > 0.0 => Ok((p, q)),
+            _ => Err(format!("invalid level [{}, {}]", price, qty)),
+        })
+        .collect()
+}
+
+/// Reads order book snapshots keyed by symbol.
+/// Fails with an `IngestError` listing every malformed book.
+pub fn load_depth(path: &Path) -> Result<OrderBooks, Box<dyn Error>> {
+    let file = path.display().to_string();
+    let data = fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", file, e))?;
+    let raw: Vec<serde_json::Value> = serde_json::from_str(&data)?;
+
+    let mut books = OrderBooks::new();
+    let mut errors = Vec::new();
+    for (i, value) in raw.into_iter().enumerate() {
+        let parsed = serde_json::from_value::<DepthSnapshot>(value)
+            .map_err(|e| e.to_string())
+            .and_then(|snapshot| {
+                let mut bids = parse_levels(&snapshot.bids)?;
+                let mut asks = parse_levels(&snapshot.asks)?;
+                bids.sort_by(|a, b| b.0.total_cmp(&a.0));
+                asks.sort_by(|a, b| a.0.total_cmp(&b.0));
+                Ok((snapshot.symbol, OrderBook { bids, asks }))
+            });
+        match parsed {
+            Ok((symbol, book)) => {
+                books.insert(symbol, book);
+            }
+            Err(reason) => errors.push(MalformedEntry { file: file.clone(), key: EntryKey::Index(i), reason }),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(Box::new(IngestError { entries: errors }));
+    }
+    Ok(books)
+}
+
+/// Walks the book of `leg`'s symbol with `amount` of the leg's input currency.
+/// Transfers pass through less their fee.
+/// Returns the proceeds after fees, or `None` if the book is missing or runs out first.
+fn fill(books: &OrderBooks, leg: &Edge, amount: f64) -> Option<f64> {
+    let levels = match leg.side {
+        Side::Sell => &books.get(&leg.symbol)?.bids,
+        Side::Buy => &books.get(&leg.symbol)?.asks,
+        Side::Transfer => return Some(amount * (1.0 - leg.fee)),
+    };
+    let mut remaining = amount;
+    let mut proceeds = 0.0;
+    for &(price, qty) in levels {
+        if remaining <= amount * FILL_TOLERANCE {
+            break;
+        }
+        if leg.side == Side::Sell {
+            let take = remaining.min(qty);
+            proceeds += take * price;
+            remaining -= take;
+        } else {
+            let spend = remaining.min(price * qty);
+            proceeds += spend / price;
+            remaining -= spend;
+        }
+    }
+    (remaining <= amount * FILL_TOLERANCE).then_some(proceeds * (1.0 - leg.fee))
+}
+
+/// Executes cycles against order book depth to find how much they can carry.
+/// A cycle is given by the edges it was detected on, so every leg is filled
+/// on the symbol and side that formed it; books are matched by symbol.
+/// Amounts are in units of the first leg's source currency.
+pub struct CycleSizer {
+    books: OrderBooks,
+}
+
+impl CycleSizer {
+    pub fn new(books: OrderBooks) -> CycleSizer {
+        CycleSizer { books }
+    }
+
+    /// Whether every trading leg has a book.
+    pub fn has_depth(&self, legs: &[Edge]) -> bool {
+        legs.iter().all(|leg| leg.side == Side::Transfer || self.books.contains_key(&leg.symbol))
+    }
+
+    /// Amount of the first currency returned after pushing `amount` around the cycle.
+    pub fn execute(&self, legs: &[Edge], amount: f64) -> Option<f64> {
+        legs.iter().try_fold(amount, |held, leg| fill(&self.books, leg, held))
+    }
+
+    /// Largest amount the books can absorb around the cycle.
+    pub fn capacity(&self, legs: &[Edge]) -> Option<f64> {
+        if !self.has_depth(legs) {
+            return None;
+        }
+        // The first book bounds the size, grossed up for transfers before it.
+        let transfers = legs.iter().take_while(|leg| leg.side == Side::Transfer);
+        let kept: f64 = transfers.clone().map(|leg| 1.0 - leg.fee).product();
+        let first = legs.get(transfers.count())?;
+        let book = &self.books[&first.symbol];
+        let mut hi = match first.side {
+            Side::Sell => book.bids.iter().map(|&(_, q)| q).sum::<f64>(),
+            Side::Buy | Side::Transfer => book.asks.iter().map(|&(p, q)| p * q).sum(),
+        } / kept;
+        if self.execute(legs, hi).is_some() {
+            return Some(hi);
+        }
+        let mut lo = 0.0;
+        for _ in 0..100 {
+            let mid = (lo + hi) / 2.0;
+            if self.execute(legs, mid).is_some() {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        Some(lo)
+    }
+
+    /// Largest amount whose round trip still returns at least what went in.
+    /// Deeper levels only get worse, so the return falls with size and bisection applies.
+    /// `None` when a leg has no book; zero when even the top of book loses money.
+    pub fn max_size(&self, legs: &[Edge]) -> Option<f64> {
+        let capacity = self.capacity(legs)?;
+        let profitable = |x: f64| self.execute(legs, x).is_some_and(|out| out >= x);
+        let (mut lo, mut hi) = (capacity * FILL_TOLERANCE, capacity);
+        if capacity == 0.0 || !profitable(lo) {
+            return Some(0.0);
+        }
+        if profitable(hi) {
+            return Some(hi);
+        }
+        for _ in 0..100 {
+            let mid = (lo + hi) / 2.0;
+            if profitable(mid) {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        Some(lo)
+    }
+
+    /// Round-trip return at `points` evenly spaced sizes up to the cycle's capacity,
+    /// as `(size, return)` pairs.
+    pub fn profit_curve(&self, legs: &[Edge], points: usize) -> Vec<(f64, f64)> {
+        let Some(capacity) = self.capacity(legs).filter(|&c| c > 0.0) else {
+            return Vec::new();
+        };
+        (1..=points)
+            .filter_map(|i| {
+                let size = capacity * i as f64 / points as f64;
+                self.execute(legs, size).map(|out| (size, out / size))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::fixtures::edge;
+
+    fn leg(symbol: &str, side: Side, fee: f64) -> Edge {
+        Edge { symbol: symbol.to_string(), side, fee, ..edge(1.0) }
+    }
+
+    #[test]
+    fn sizes_on_the_detected_symbols() {
+        let mut books = OrderBooks::new();
+        books.insert("AB".to_string(), OrderBook { bids: vec![(1.1, 10.0)], asks: vec![(1.0, 5.0)] });
+        // A second symbol for the same pair must not be used.
+        books.insert("BA".to_string(), OrderBook { bids: vec![(1.0, 1.0)], asks: vec![(1.0, 1.0)] });
+        let sizer = CycleSizer::new(books);
+        let legs = [leg("AB", Side::Sell, 0.0), leg("AB", Side::Buy, 0.0)];
+
+        // Buying back is capped at 5 A, i.e. 5 B of proceeds from 5 / 1.1 A.
+        let max = sizer.max_size(&legs).unwrap();
+        assert!((max - 5.0 / 1.1).abs() < 1e-9);
+        assert!((sizer.execute(&legs, 1.0).unwrap() - 1.1).abs() < 1e-12);
+        assert!(sizer.max_size(&[leg("AB", Side::Sell, 0.0), leg("CD", Side::Buy, 0.0)]).is_none());
+    }
+
+    #[test]
+    fn transfers_pass_through_less_their_fee() {
+        let mut books = OrderBooks::new();
+        books.insert("AB".to_string(), OrderBook { bids: vec![(1.1, 10.0)], asks: vec![(1.0, 100.0)] });
+        let sizer = CycleSizer::new(books);
+        let legs = [leg("A", Side::Transfer, 0.5), leg("AB", Side::Sell, 0.0), leg("AB", Side::Buy, 0.0)];
+        assert!((sizer.execute(&legs, 2.0).unwrap() - 1.1).abs() < 1e-12);
+        // The 10 A bid absorbs 20 A sent through the transfer.
+        assert!((sizer.capacity(&legs).unwrap() - 20.0).abs() < 1e-9);
+    }
+}
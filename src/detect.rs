@@ -0,0 +1,280 @@
+//! Negative cycle detection with Bellman-Ford.
+//!
+//! `enumerate` lists every short cycle and `mean` finds the best cycle per leg.
+
+pub mod enumerate;
+pub mod mean;
+
+use crate::graph::{cycle_edges, cycle_nodes, ArbGraph};
+use clap::ValueEnum;
+use petgraph::algo::{find_negative_cycle, tarjan_scc};
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::{Dfs, EdgeRef, NodeIndexable, Reversed};
+
+/// Selects where Bellman-Ford is started from when looking for negative cycles.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum DetectionMode {
+    /// A single run from the first remaining node; only cycles reachable from it are seen.
+    FromFirstNode,
+    /// One run per strongly connected component from a virtual super-source,
+    /// so no component containing a negative cycle is missed.
+    PerScc,
+}
+
+/// A negative cycle and the strongly connected component it lies in.
+pub struct SccCycle {
+    /// Index of the component in `tarjan_scc` order.
+    pub scc: usize,
+    /// Cycle nodes in traversal order; the last node links back to the first.
+    pub nodes: Vec<NodeIndex>,
+    /// The exact edges traversed, `edges[i]` leaving `nodes[i]`.
+    pub edges: Vec<EdgeIndex>,
+}
+
+/// Weight added to every edge so that only cycles returning at least
+/// `min_return_bps` basis points per leg stay negative: a cycle of `k` legs
+/// must return `(1 + min_return_bps / 10^4)^k` or more.
+pub fn weight_shift(min_return_bps: f64) -> f64 {
+    (1.0 + min_return_bps / 10_000.0).log2()
+}
+
+/// Canonical form of a cycle given by its currencies in traversal order: rotated
+/// to start at the smallest name. The direction is kept, so a loop and its
+/// reverse, which trade different sides, stay distinct.
+pub fn canonical_cycle(currencies: &[String]) -> Vec<String> {
+    let start = (0..currencies.len()).min_by_key(|&i| &currencies[i]).unwrap_or(0);
+    currencies[start..].iter().chain(&currencies[..start]).cloned().collect()
+}
+
+/// Runs Bellman-Ford restricted to one strongly connected component, or any
+/// other set of nodes, starting from a virtual super-source with a zero-weight
+/// edge to every member. Every edge weight is increased by `shift`.
+/// Returns the cycle's edges in traversal order if the component contains a negative cycle.
+fn scc_negative_cycle(graph: &ArbGraph, component: &[NodeIndex], shift: f64) -> Option<Vec<EdgeIndex>> {
+    let mut in_scc = vec![false; graph.node_bound()];
+    for node in component {
+        in_scc[node.index()] = true;
+    }
+    let mut distance = vec![0.0; graph.node_bound()];
+    // The edge each node was last relaxed through, so parallel edges stay apart.
+    let mut predecessor: Vec<Option<EdgeIndex>> = vec![None; graph.node_bound()];
+
+    // Paths from the super-source have at most `component.len()` edges, so a
+    // relaxation in the final round can only come from a negative cycle.
+    let mut last_relaxed = None;
+    for _ in 0..component.len() {
+        last_relaxed = None;
+        for &node in component {
+            for edge in graph.edges(node) {
+                let target = edge.target();
+                let weight = edge.weight().log_weight() + shift;
+                if in_scc[target.index()] && distance[node.index()] + weight < distance[target.index()] {
+                    distance[target.index()] = distance[node.index()] + weight;
+                    predecessor[target.index()] = Some(edge.id());
+                    last_relaxed = Some(target);
+                }
+            }
+        }
+        last_relaxed?;
+    }
+
+    let previous = |node: NodeIndex| -> Option<(EdgeIndex, NodeIndex)> {
+        let edge = predecessor[node.index()]?;
+        Some((edge, graph.edge_endpoints(edge)?.0))
+    };
+
+    // Walk back far enough to be guaranteed to stand on the cycle itself.
+    let mut node = last_relaxed?;
+    for _ in 0..component.len() {
+        node = previous(node)?.1;
+    }
+    let mut cycle = Vec::new();
+    let mut current = node;
+    loop {
+        let (edge, source) = previous(current)?;
+        cycle.push(edge);
+        current = source;
+        if current == node {
+            break;
+        }
+    }
+    cycle.reverse();
+    Some(cycle)
+}
+
+/// Finds one negative cycle in every strongly connected component that has one,
+/// after shifting every weight by `shift` (see `weight_shift`).
+/// Components are numbered in the order returned by `tarjan_scc`.
+pub fn negative_cycles_per_scc(graph: &ArbGraph, shift: f64) -> Vec<SccCycle> {
+    tarjan_scc(graph)
+        .iter()
+        .enumerate()
+        .filter_map(|(scc, component)| {
+            scc_negative_cycle(graph, component, shift).map(|edges| SccCycle { scc, nodes: cycle_nodes(graph, &edges), edges })
+        })
+        .collect()
+}
+
+/// Nodes on some path from `from` to `to`: reachable from `from` and reaching `to`.
+fn nodes_between(graph: &ArbGraph, from: NodeIndex, to: NodeIndex) -> Vec<NodeIndex> {
+    let mut forward = vec![false; graph.node_bound()];
+    let mut dfs = Dfs::new(graph, from);
+    while let Some(node) = dfs.next(graph) {
+        forward[node.index()] = true;
+    }
+    let reversed = Reversed(graph);
+    let mut dfs = Dfs::new(reversed, to);
+    let mut between = Vec::new();
+    while let Some(node) = dfs.next(reversed) {
+        if forward[node.index()] {
+            between.push(node);
+        }
+    }
+    between
+}
+
+/// Looks for a negative cycle through any of the `changed` edges, running
+/// Bellman-Ford only over the nodes such a cycle can visit. As in
+/// `negative_finder`, only cycles returning at least `min_return_bps` basis
+/// points per leg count.
+/// Assumes the graph had no such cycle before the edges changed, so any new
+/// one must use a changed edge.
+/// Returns the cycle's edges in traversal order.
+pub fn negative_cycle_through(graph: &ArbGraph, changed: &[EdgeIndex], min_return_bps: f64) -> Option<Vec<EdgeIndex>> {
+    let shift = weight_shift(min_return_bps);
+    changed.iter().find_map(|&edge| {
+        let (source, target) = graph.edge_endpoints(edge)?;
+        let region = nodes_between(graph, target, source);
+        if region.is_empty() {
+            return None;
+        }
+        scc_negative_cycle(graph, &region, shift)
+    })
+}
+
+/// Returns the index of the strongly connected component containing `node`.
+fn scc_of(graph: &ArbGraph, node: NodeIndex) -> usize {
+    tarjan_scc(graph)
+        .iter()
+        .position(|component| component.contains(&node))
+        .unwrap_or(0)
+}
+
+/// Detects a negative cycle in the graph, counting only cycles that return at
+/// least `min_return_bps` basis points per leg; 0 accepts any profit.
+/// Returns the cycle and the strongly connected component it came from, if found.
+pub fn negative_finder(graph: &ArbGraph, mode: DetectionMode, min_return_bps: f64) -> Option<SccCycle> {
+    let shift = weight_shift(min_return_bps);
+    println!("Checking for negative cycles...");
+    let Some(first_node) = graph.node_indices().next() else {
+        println!("No negative cycle detected.");
+        return None;
+    };
+    let found = match mode {
+        // petgraph needs bare weights and only reports nodes; Bellman-Ford
+        // relaxes through the lightest edges. `map` keeps indices stable.
+        DetectionMode::FromFirstNode => find_negative_cycle(
+            &graph.map(|_, label| label.clone(), |_, edge| edge.log_weight() + shift),
+            first_node,
+        )
+        .map(|nodes| SccCycle {
+            scc: scc_of(graph, nodes[0]),
+            edges: cycle_edges(graph, &nodes),
+            nodes,
+        }),
+        DetectionMode::PerScc => {
+            let cycles = negative_cycles_per_scc(graph, shift);
+            for cycle in &cycles {
+                let path: Vec<&str> = cycle.nodes.iter().map(|&n| graph[n].as_str()).collect();
+                println!("  SCC {}: {}", cycle.scc, path.join(" -> "));
+            }
+            cycles.into_iter().next()
+        }
+    };
+    if let Some(cycle) = found {
+        println!("Negative cycle found in SCC {}.", cycle.scc);
+        Some(cycle)
+    } else {
+        println!("No negative cycle detected.");
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::enumerate::enumerate_negative_cycles;
+    use crate::graph::fixtures::{assert_closed, graph, labels};
+
+    #[test]
+    fn per_scc_finds_cycle_in_graph_with_holes() {
+        // C is removed; A -> B -> D -> A returns 1.1 * 1.0 * 1.0.
+        let graph = graph(5, &[(0, 1, 1.1), (1, 3, 1.0), (3, 0, 1.0), (1, 2, 2.0), (2, 0, 2.0), (4, 0, 1.0)], &[2]);
+        let cycles = negative_cycles_per_scc(&graph, 0.0);
+        assert_eq!(cycles.len(), 1);
+        let cycle = &cycles[0];
+        assert_closed(&graph, &cycle.edges);
+        assert_eq!(cycle.nodes, cycle_nodes(&graph, &cycle.edges));
+        let mut visited = labels(&graph, &cycle.nodes);
+        visited.sort();
+        assert_eq!(visited, ["A", "B", "D"]);
+    }
+
+    #[test]
+    fn no_cycle_without_profit() {
+        let graph = graph(3, &[(0, 1, 2.0), (1, 2, 0.5), (2, 0, 1.0), (1, 0, 0.5)], &[]);
+        assert!(negative_cycles_per_scc(&graph, 0.0).is_empty());
+        assert!(negative_finder(&graph, DetectionMode::FromFirstNode, 0.0).is_none());
+    }
+
+    #[test]
+    fn per_scc_finds_cycle_first_node_misses() {
+        // Nothing is reachable from A, the first node; B <-> C is profitable.
+        let graph = graph(3, &[(1, 0, 1.0), (1, 2, 1.1), (2, 1, 1.0)], &[]);
+        assert!(negative_finder(&graph, DetectionMode::FromFirstNode, 0.0).is_none());
+        let cycle = negative_finder(&graph, DetectionMode::PerScc, 0.0).unwrap();
+        assert_closed(&graph, &cycle.edges);
+        assert_eq!(cycle.edges.len(), 2);
+    }
+
+    #[test]
+    fn cycle_through_changed_edge() {
+        let mut graph = graph(4, &[(0, 1, 1.0), (1, 2, 1.0), (2, 0, 0.9), (2, 3, 1.0), (3, 2, 0.9)], &[]);
+        let closing = graph.find_edge(NodeIndex::new(2), NodeIndex::new(0)).unwrap();
+        let unrelated = graph.find_edge(NodeIndex::new(3), NodeIndex::new(2)).unwrap();
+        assert!(negative_cycle_through(&graph, &[closing, unrelated], 0.0).is_none());
+
+        graph[closing].price = 1.2;
+        let edges = negative_cycle_through(&graph, &[unrelated, closing], 0.0).unwrap();
+        assert_closed(&graph, &edges);
+        assert!(edges.contains(&closing));
+        assert_eq!(labels(&graph, &cycle_nodes(&graph, &edges)).len(), 3);
+        // 1.2 over three legs is about 627 basis points per leg.
+        assert!(negative_cycle_through(&graph, &[closing], 600.0).is_some());
+        assert!(negative_cycle_through(&graph, &[closing], 650.0).is_none());
+    }
+
+    #[test]
+    fn threshold_agrees_between_enumeration_and_bellman_ford() {
+        // (rates around one cycle, minimum return in basis points)
+        let cases: [(&[f64], f64); 9] = [
+            (&[4.0, 1.0], 10_000.0),
+            (&[4.000001, 1.0], 10_000.0),
+            (&[3.999999, 1.0], 10_000.0),
+            (&[1.01, 1.01, 1.01], 100.0),
+            (&[1.0101, 1.01, 1.01], 100.0),
+            (&[1.0099, 1.01, 1.01], 100.0),
+            (&[2.0, 0.5], 0.0),
+            (&[2.0, 0.5000001], 0.0),
+            (&[1.05, 1.05, 0.95, 1.0], 250.0),
+        ];
+        for (rates, bps) in cases {
+            let n = rates.len();
+            let edges: Vec<_> = rates.iter().enumerate().map(|(i, &rate)| (i, (i + 1) % n, rate)).collect();
+            let graph = graph(n, &edges, &[]);
+            let cleared = enumerate_negative_cycles(&graph, n).iter().any(|c| c.clears_threshold(&graph, bps));
+            let found = negative_finder(&graph, DetectionMode::PerScc, bps).is_some();
+            assert_eq!(cleared, found, "{:?} at {} bps", rates, bps);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! Exhaustive enumeration and ranking of short arbitrage cycles.
+
+use crate::detect::weight_shift;
+use crate::graph::ArbGraph;
+use clap::ValueEnum;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::{EdgeRef, NodeIndexable};
+
+/// A simple negative cycle with its currency path and product of rates.
+pub struct ArbitrageCycle {
+    /// Cycle nodes in traversal order, starting from the lowest index or the anchor.
+    pub nodes: Vec<NodeIndex>,
+    /// The exact edges traversed, `edges[i]` leaving `nodes[i]`; parallel
+    /// edges between the same currencies make distinct cycles.
+    pub edges: Vec<EdgeIndex>,
+    /// Labels of `nodes`.
+    pub currencies: Vec<String>,
+    /// Product of rates around the cycle.
+    pub rate_product: f64,
+}
+
+impl ArbitrageCycle {
+    /// Per-leg geometric mean of the rates, so long and short cycles compare fairly.
+    pub fn geometric_mean_return(&self) -> f64 {
+        self.rate_product.powf(1.0 / self.nodes.len() as f64)
+    }
+
+    /// Whether the cycle returns at least `min_return_bps` basis points per leg.
+    /// Sums the same shifted edge weights `negative_finder` relaxes, so the two
+    /// agree on cycles right at the threshold.
+    pub fn clears_threshold(&self, graph: &ArbGraph, min_return_bps: f64) -> bool {
+        let shift = weight_shift(min_return_bps);
+        self.edges.iter().map(|&edge| graph[edge].log_weight() + shift).sum::<f64>() < 0.0
+    }
+}
+
+/// Ordering used when ranking cycles by opportunity size.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum RankBy {
+    /// Product of rates around the whole cycle.
+    Return,
+    /// Per-leg geometric mean return.
+    GeometricMean,
+}
+
+/// Depth-first search state shared by `enumerate_negative_cycles` and `best_cycle_through`.
+struct CycleSearch<'a> {
+    graph: &'a ArbGraph,
+    max_len: usize,
+    start: NodeIndex,
+    /// Visit nodes below `start` too, so every cycle through it is found.
+    anchored: bool,
+    path: Vec<NodeIndex>,
+    /// Edges between consecutive `path` nodes.
+    edges: Vec<EdgeIndex>,
+    on_path: Vec<bool>,
+    cycles: Vec<ArbitrageCycle>,
+}
+
+impl<'a> CycleSearch<'a> {
+    fn new(graph: &'a ArbGraph, max_len: usize, anchored: bool) -> Self {
+        CycleSearch {
+            graph,
+            max_len,
+            start: NodeIndex::new(0),
+            anchored,
+            path: Vec::with_capacity(max_len),
+            edges: Vec::with_capacity(max_len),
+            on_path: vec![false; graph.node_bound()],
+            cycles: Vec::new(),
+        }
+    }
+
+    /// Records the negative cycles closing back at `start`.
+    fn search_from(&mut self, start: NodeIndex) {
+        self.start = start;
+        self.path.push(start);
+        self.on_path[start.index()] = true;
+        self.extend(0.0);
+        self.on_path[start.index()] = false;
+        self.path.pop();
+    }
+
+    /// Extends the current path by one edge, recording every way back to `start`
+    /// that closes a negative cycle.
+    fn extend(&mut self, weight: f64) {
+        let node = *self.path.last().unwrap();
+        for edge in self.graph.edges(node) {
+            let target = edge.target();
+            let total = weight + edge.weight().log_weight();
+            if target == self.start {
+                if total < 0.0 {
+                    let mut edges = self.edges.clone();
+                    edges.push(edge.id());
+                    self.cycles.push(ArbitrageCycle {
+                        nodes: self.path.clone(),
+                        edges,
+                        currencies: self.path.iter().map(|&n| self.graph[n].clone()).collect(),
+                        rate_product: 2f64.powf(-total),
+                    });
+                }
+            } else if (self.anchored || target > self.start)
+                && !self.on_path[target.index()]
+                && self.path.len() < self.max_len
+            {
+                self.path.push(target);
+                self.edges.push(edge.id());
+                self.on_path[target.index()] = true;
+                self.extend(total);
+                self.on_path[target.index()] = false;
+                self.edges.pop();
+                self.path.pop();
+            }
+        }
+    }
+}
+
+/// Enumerates every simple negative cycle with at most `max_len` hops without
+/// mutating the graph. Each cycle is reported once, starting from its lowest node index.
+pub fn enumerate_negative_cycles(graph: &ArbGraph, max_len: usize) -> Vec<ArbitrageCycle> {
+    let mut search = CycleSearch::new(graph, max_len, false);
+    for start in graph.node_indices() {
+        search.search_from(start);
+    }
+    search.cycles
+}
+
+/// Finds the best simple negative cycle of at most `max_len` hops passing
+/// through `anchor`, with its nodes rotated to start there.
+/// Returns `None` when no such cycle exists or `anchor` is not in the graph.
+pub fn best_cycle_through(graph: &ArbGraph, anchor: NodeIndex, max_len: usize, rank_by: RankBy) -> Option<ArbitrageCycle> {
+    if !graph.contains_node(anchor) {
+        return None;
+    }
+    let mut search = CycleSearch::new(graph, max_len, true);
+    search.search_from(anchor);
+    rank_cycles(&mut search.cycles, rank_by, 1);
+    search.cycles.pop()
+}
+
+/// Sorts cycles from the most to the least profitable and keeps the best `k`,
+/// so reports follow opportunity size rather than detection order.
+pub fn rank_cycles(cycles: &mut Vec<ArbitrageCycle>, rank_by: RankBy, k: usize) {
+    let key = |cycle: &ArbitrageCycle| match rank_by {
+        RankBy::Return => cycle.rate_product,
+        RankBy::GeometricMean => cycle.geometric_mean_return(),
+    };
+    cycles.sort_by(|a, b| key(b).total_cmp(&key(a)));
+    cycles.truncate(k);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::canonical_cycle;
+    use crate::graph::fixtures::{assert_closed, graph};
+    use std::collections::HashSet;
+
+    #[test]
+    fn reports_each_cycle_once() {
+        // Every edge of the complete graph on A..D (C removed from a 5-node
+        // index space) gains 1%, so every simple cycle is negative.
+        let mut edges = Vec::new();
+        for a in [0, 1, 3, 4] {
+            for b in [0, 1, 3, 4] {
+                if a != b {
+                    edges.push((a, b, 1.01));
+                }
+            }
+        }
+        let graph = graph(5, &edges, &[2]);
+        let cycles = enumerate_negative_cycles(&graph, 4);
+        // 6 two-leg, 8 three-leg and 6 four-leg cycles.
+        assert_eq!(cycles.len(), 20);
+        let distinct: HashSet<Vec<String>> = cycles.iter().map(|c| canonical_cycle(&c.currencies)).collect();
+        assert_eq!(distinct.len(), 20);
+        for cycle in &cycles {
+            assert_closed(&graph, &cycle.edges);
+            assert_eq!(cycle.nodes[0], *cycle.nodes.iter().min().unwrap());
+            assert!((cycle.rate_product - 1.01f64.powi(cycle.nodes.len() as i32)).abs() < 1e-12);
+        }
+        assert_eq!(enumerate_negative_cycles(&graph, 2).len(), 6);
+    }
+
+    #[test]
+    fn parallel_edges_make_distinct_cycles() {
+        let graph = graph(2, &[(0, 1, 1.1), (0, 1, 1.2), (1, 0, 1.0)], &[]);
+        let mut cycles = enumerate_negative_cycles(&graph, 2);
+        assert_eq!(cycles.len(), 2);
+        assert_ne!(cycles[0].edges, cycles[1].edges);
+        rank_cycles(&mut cycles, RankBy::Return, 1);
+        assert!((cycles[0].rate_product - 1.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn best_cycle_starts_at_anchor() {
+        // A -> B -> C -> A returns 1.06, A -> B -> A returns 1.05 but more per leg.
+        let graph = graph(3, &[(0, 1, 1.05), (1, 0, 1.0), (1, 2, 1.0), (2, 0, 1.06 / 1.05)], &[]);
+        let cycle = best_cycle_through(&graph, NodeIndex::new(2), 3, RankBy::Return).unwrap();
+        assert_eq!(cycle.currencies, ["C", "A", "B"]);
+        let cycle = best_cycle_through(&graph, NodeIndex::new(0), 3, RankBy::Return).unwrap();
+        assert_eq!(cycle.currencies, ["A", "B", "C"]);
+        let cycle = best_cycle_through(&graph, NodeIndex::new(0), 3, RankBy::GeometricMean).unwrap();
+        assert_eq!(cycle.currencies, ["A", "B"]);
+    }
+}
@@ -0,0 +1,292 @@
+//! Minimum mean cycle solvers: the cycle with the best average log-return per leg.
+
+use crate::graph::{min_edge_weight, ArbGraph};
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::{EdgeRef, IntoEdgeReferences, NodeIndexable};
+
+/// A cycle with the lowest average edge weight, i.e. the best log-return per leg.
+pub struct MeanCycle {
+    /// Cycle nodes in traversal order.
+    pub nodes: Vec<NodeIndex>,
+    /// Average `-log2(rate)` per leg.
+    pub mean_weight: f64,
+}
+
+impl MeanCycle {
+    /// Rate earned per leg on average, `2^-mean_weight`.
+    pub fn per_leg_return(&self) -> f64 {
+        2f64.powf(-self.mean_weight)
+    }
+}
+
+/// Mean weight per edge of a cycle given in traversal order.
+fn cycle_mean_weight(graph: &ArbGraph, nodes: &[NodeIndex]) -> f64 {
+    let total: f64 = nodes
+        .iter()
+        .zip(nodes.iter().cycle().skip(1))
+        .map(|(&source, &target)| min_edge_weight(graph, source, target))
+        .sum();
+    total / nodes.len() as f64
+}
+
+/// Karp's minimum mean cycle algorithm, run from a virtual super-source so
+/// every component is covered. O(V * E) time and O(V^2) memory.
+pub fn karp_min_mean_cycle(graph: &ArbGraph) -> Option<MeanCycle> {
+    let n = graph.node_count();
+    let bound = graph.node_bound();
+    // walk[k][v]: lightest walk of exactly k edges ending at v.
+    let mut walk = vec![vec![f64::INFINITY; bound]; n + 1];
+    let mut predecessor = vec![vec![None; bound]; n + 1];
+    for node in graph.node_indices() {
+        walk[0][node.index()] = 0.0;
+    }
+    for k in 1..=n {
+        for edge in graph.edge_references() {
+            let (source, target) = (edge.source().index(), edge.target().index());
+            let candidate = walk[k - 1][source] + edge.weight().log_weight();
+            if candidate < walk[k][target] {
+                walk[k][target] = candidate;
+                predecessor[k][target] = Some(edge.source());
+            }
+        }
+    }
+
+    // Karp's theorem: the minimum mean is min over v of max over k of
+    // (walk[n][v] - walk[k][v]) / (n - k).
+    let mut best: Option<(NodeIndex, f64)> = None;
+    for node in graph.node_indices() {
+        let v = node.index();
+        if walk[n][v].is_infinite() {
+            continue;
+        }
+        let worst = (0..n)
+            .filter(|&k| walk[k][v].is_finite())
+            .map(|k| (walk[n][v] - walk[k][v]) / (n - k) as f64)
+            .fold(f64::NEG_INFINITY, f64::max);
+        if best.is_none_or(|(_, mean)| worst < mean) {
+            best = Some((node, worst));
+        }
+    }
+    let (end, _) = best?;
+
+    // The n-edge walk ending at the minimiser repeats a node; split it into
+    // simple cycles and keep the lightest.
+    let mut path = vec![end];
+    for k in (1..=n).rev() {
+        path.push(predecessor[k][path.last()?.index()]?);
+    }
+    path.reverse();
+    let mut stack: Vec<NodeIndex> = Vec::new();
+    let mut best_cycle: Option<MeanCycle> = None;
+    for node in path {
+        if let Some(position) = stack.iter().position(|&n| n == node) {
+            let nodes = stack.split_off(position);
+            let mean_weight = cycle_mean_weight(graph, &nodes);
+            if best_cycle.as_ref().is_none_or(|c| mean_weight < c.mean_weight) {
+                best_cycle = Some(MeanCycle { nodes, mean_weight });
+            }
+        }
+        stack.push(node);
+    }
+    best_cycle
+}
+
+/// Howard's policy iteration for the minimum mean cycle, run per strongly
+/// connected component. Usually far faster than Karp on sparse graphs.
+pub fn howard_min_mean_cycle(graph: &ArbGraph) -> Option<MeanCycle> {
+    tarjan_scc(graph)
+        .iter()
+        .filter_map(|component| howard_scc(graph, component))
+        .min_by(|a, b| a.mean_weight.total_cmp(&b.mean_weight))
+}
+
+/// Runs Howard's algorithm on one strongly connected component.
+fn howard_scc(graph: &ArbGraph, component: &[NodeIndex]) -> Option<MeanCycle> {
+    const EPSILON: f64 = 1e-12;
+    const MAX_ITERATIONS: usize = 10_000;
+
+    let n = component.len();
+    let mut local = vec![usize::MAX; graph.node_bound()];
+    for (i, node) in component.iter().enumerate() {
+        local[node.index()] = i;
+    }
+    let out_edges: Vec<Vec<(usize, f64)>> = component
+        .iter()
+        .map(|&node| {
+            graph
+                .edges(node)
+                .filter(|edge| local[edge.target().index()] != usize::MAX)
+                .map(|edge| (local[edge.target().index()], edge.weight().log_weight()))
+                .collect()
+        })
+        .collect();
+    if out_edges.iter().any(Vec::is_empty) {
+        // Only a single node without a self-loop has no edge inside its component.
+        return None;
+    }
+
+    // Start from the cheapest outgoing edge of every node.
+    let mut policy: Vec<(usize, f64)> = out_edges
+        .iter()
+        .map(|edges| *edges.iter().min_by(|a, b| a.1.total_cmp(&b.1)).unwrap())
+        .collect();
+    let mut eta = vec![0.0; n];
+    let mut value = vec![0.0; n];
+
+    for _ in 0..MAX_ITERATIONS {
+        // Value determination: every policy component holds exactly one cycle,
+        // whose mean becomes eta for all nodes draining into it.
+        let mut state = vec![0u8; n];
+        for start in 0..n {
+            if state[start] != 0 {
+                continue;
+            }
+            let mut walk = Vec::new();
+            let mut node = start;
+            while state[node] == 0 {
+                state[node] = 1;
+                walk.push(node);
+                node = policy[node].0;
+            }
+            if state[node] == 1 {
+                let position = walk.iter().position(|&w| w == node).unwrap();
+                let cycle = walk.split_off(position);
+                let mean = cycle.iter().map(|&c| policy[c].1).sum::<f64>() / cycle.len() as f64;
+                eta[node] = mean;
+                value[node] = 0.0;
+                state[node] = 2;
+                for &c in cycle[1..].iter().rev() {
+                    let (next, weight) = policy[c];
+                    eta[c] = mean;
+                    value[c] = weight - mean + value[next];
+                    state[c] = 2;
+                }
+            }
+            for &w in walk.iter().rev() {
+                let (next, weight) = policy[w];
+                eta[w] = eta[next];
+                value[w] = weight - eta[w] + value[next];
+                state[w] = 2;
+            }
+        }
+
+        // Policy improvement: first move towards cheaper cycles, then along
+        // cheaper paths to the same cycle.
+        let mut changed = false;
+        for node in 0..n {
+            let &(target, weight) = out_edges[node].iter().min_by(|a, b| eta[a.0].total_cmp(&eta[b.0])).unwrap();
+            if eta[target] < eta[node] - EPSILON {
+                policy[node] = (target, weight);
+                changed = true;
+            }
+        }
+        if !changed {
+            for node in 0..n {
+                let mut best = value[node];
+                for &(target, weight) in &out_edges[node] {
+                    let candidate = weight - eta[node] + value[target];
+                    if (eta[target] - eta[node]).abs() <= EPSILON && candidate < best - EPSILON {
+                        best = candidate;
+                        policy[node] = (target, weight);
+                        changed = true;
+                    }
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    // Follow the policy from the node with the lowest eta until it loops.
+    let start = (0..n).min_by(|&a, &b| eta[a].total_cmp(&eta[b]))?;
+    let mut seen = vec![false; n];
+    let mut node = start;
+    while !seen[node] {
+        seen[node] = true;
+        node = policy[node].0;
+    }
+    let mut nodes = vec![component[node]];
+    let mut next = policy[node].0;
+    while next != node {
+        nodes.push(component[next]);
+        next = policy[next].0;
+    }
+    let mean_weight = nodes
+        .iter()
+        .map(|n| local[n.index()])
+        .map(|i| policy[i].1)
+        .sum::<f64>()
+        / nodes.len() as f64;
+    Some(MeanCycle { nodes, mean_weight })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::fixtures::graph;
+    use petgraph::visit::IntoNodeIdentifiers;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// Minimum mean over every simple cycle, found by exhaustive search.
+    fn brute_force_min_mean(graph: &ArbGraph) -> Option<f64> {
+        fn extend(graph: &ArbGraph, path: &mut Vec<NodeIndex>, best: &mut Option<f64>) {
+            let (start, last) = (path[0], *path.last().unwrap());
+            let mut targets: Vec<NodeIndex> = graph.neighbors(last).collect();
+            targets.sort();
+            targets.dedup();
+            for target in targets {
+                if target == start {
+                    let mean = cycle_mean_weight(graph, path);
+                    if best.is_none_or(|b| mean < b) {
+                        *best = Some(mean);
+                    }
+                } else if target > start && !path.contains(&target) {
+                    path.push(target);
+                    extend(graph, path, best);
+                    path.pop();
+                }
+            }
+        }
+        let mut best = None;
+        for start in graph.node_identifiers() {
+            extend(graph, &mut vec![start], &mut best);
+        }
+        best
+    }
+
+    #[test]
+    fn karp_and_howard_match_brute_force() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..200 {
+            let nodes = rng.gen_range(2..8);
+            let edges: Vec<(usize, usize, f64)> = (0..rng.gen_range(1..3 * nodes))
+                .map(|_| (rng.gen_range(0..nodes), rng.gen_range(0..nodes), rng.gen_range(0.8..1.2)))
+                .filter(|&(a, b, _)| a != b)
+                .collect();
+            let removed = if rng.gen_bool(0.5) { vec![rng.gen_range(0..nodes)] } else { Vec::new() };
+            let graph = graph(nodes, &edges, &removed);
+
+            let expected = brute_force_min_mean(&graph);
+            for (solver, found) in [("karp", karp_min_mean_cycle(&graph)), ("howard", howard_min_mean_cycle(&graph))] {
+                match (expected, found) {
+                    (None, None) => {}
+                    (Some(expected), Some(cycle)) => {
+                        assert!((cycle.mean_weight - expected).abs() < 1e-9, "{}: {} != {}", solver, cycle.mean_weight, expected);
+                        assert!((cycle_mean_weight(&graph, &cycle.nodes) - expected).abs() < 1e-9, "{}", solver);
+                    }
+                    (expected, found) => panic!("{}: expected {:?}, found {:?}", solver, expected, found.map(|c| c.nodes)),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn acyclic_graph_has_no_mean_cycle() {
+        let graph = graph(3, &[(0, 1, 2.0), (1, 2, 2.0)], &[]);
+        assert!(karp_min_mean_cycle(&graph).is_none());
+        assert!(howard_min_mean_cycle(&graph).is_none());
+    }
+}
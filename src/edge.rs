@@ -0,0 +1,86 @@
+//! Trade and transfer edges of the arbitrage graph.
+
+use serde::Serialize;
+use std::fmt;
+
+/// What following an edge does, from the symbol's base currency point of view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    /// Spend the other currency on the base, filling at the ask.
+    Buy,
+    /// Sell the base for the other currency, filling at the bid.
+    Sell,
+    /// Move an asset between exchanges at par.
+    Transfer,
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Side::Buy => "BUY",
+            Side::Sell => "SELL",
+            Side::Transfer => "TRANSFER",
+        })
+    }
+}
+
+/// One way of turning the source node's currency into the target's.
+#[derive(Clone, Debug)]
+pub struct Edge {
+    /// Symbol traded, or the asset moved by a transfer.
+    pub symbol: String,
+    pub side: Side,
+    /// `raw_price` parsed; 1 for transfers.
+    pub price: f64,
+    /// Price as it appeared in the input: the bid for sells, the ask for buys.
+    pub raw_price: String,
+    /// Fraction of the proceeds lost to trading or withdrawal fees.
+    pub fee: f64,
+    /// `LOT_SIZE` order quantity bounds in the base currency, when known.
+    pub min_qty: Option<f64>,
+    pub max_qty: Option<f64>,
+    /// Base quantity quoted at `price`, when the input has it.
+    pub top_qty: Option<f64>,
+    /// Exchange or file the quote came from.
+    pub source: String,
+    /// When the quote was observed, for inputs that say so.
+    pub timestamp: Option<String>,
+    /// Set by removal strategies that keep the edge but take it out of play.
+    pub consumed: bool,
+}
+
+impl Edge {
+    /// Units of the target currency received per unit of the source currency, net of fees.
+    pub fn rate(&self) -> f64 {
+        let gross = match self.side {
+            Side::Sell => self.price,
+            Side::Buy => 1.0 / self.price,
+            Side::Transfer => 1.0,
+        };
+        gross * (1.0 - self.fee)
+    }
+
+    /// `-log2(rate)`, the weight detection runs on; infinite once consumed.
+    pub fn log_weight(&self) -> f64 {
+        if self.consumed {
+            return f64::INFINITY;
+        }
+        let trade = match self.side {
+            Side::Sell => -self.price.log2(),
+            Side::Buy => self.price.log2(),
+            Side::Transfer => 0.0,
+        };
+        trade - (1.0 - self.fee).log2()
+    }
+}
+
+/// Trading instruction, e.g. `SELL ETHBTC @ 0.05759`.
+impl fmt::Display for Edge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.side {
+            Side::Transfer => write!(f, "TRANSFER {}", self.symbol),
+            side => write!(f, "{} {} @ {}", side, self.symbol, self.raw_price),
+        }
+    }
+}
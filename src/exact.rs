@@ -0,0 +1,143 @@
+//! Exact verification of cycle returns from the original price strings.
+//!
+//! Detection works on `log2` weights, so a cycle returning a hair over 1 may
+//! be rounding error. Here every rate is rebuilt as a ratio of integers from
+//! the decimal strings in the price file, and the product around the cycle is
+//! compared against the threshold without any rounding.
+
+use crate::edge::{Edge, Side};
+use crate::graph::ArbGraph;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use petgraph::graph::EdgeIndex;
+
+fn one() -> BigRational {
+    BigRational::from_integer(BigInt::from(1))
+}
+
+/// Parses a plain or scientific decimal such as `0.05759` or `1e-8` exactly.
+fn parse_decimal(s: &str) -> Result<BigRational, String> {
+    let invalid = || format!("'{}' is not a non-negative decimal", s);
+    let s = s.trim();
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], s[i + 1..].parse::<i32>().map_err(|_| invalid())?),
+        None => (s, 0),
+    };
+    let mantissa = mantissa.strip_prefix('+').unwrap_or(mantissa);
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", whole, fraction);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let numerator: BigInt = digits.parse().map_err(|_| invalid())?;
+    let scale = i32::try_from(fraction.len()).map_err(|_| invalid())?.checked_sub(exponent).ok_or_else(invalid)?;
+    let power = BigInt::from(10).pow(scale.unsigned_abs());
+    Ok(if scale >= 0 {
+        BigRational::new(numerator, power)
+    } else {
+        BigRational::from_integer(numerator * power)
+    })
+}
+
+/// Exact rate of an edge: the raw price, inverted for buys, times one minus the fee.
+/// Only the price is exact. The fee is the shortest decimal form of the edge's
+/// float fee, so a fee combined from a discount or a transfer delay already
+/// carries that float's rounding, e.g. `0.00015000000000000001`.
+fn exact_rate(edge: &Edge) -> Result<BigRational, String> {
+    let price = parse_decimal(&edge.raw_price)?;
+    let gross = match edge.side {
+        Side::Sell => price,
+        Side::Buy if *price.numer() == BigInt::from(0) => return Err("zero price".to_string()),
+        Side::Buy => price.recip(),
+        Side::Transfer => one(),
+    };
+    let fee = parse_decimal(&edge.fee.to_string())?;
+    if fee > one() {
+        return Err("fee above 1".to_string());
+    }
+    Ok(gross * (one() - fee))
+}
+
+/// Whether the cycle through `edges` returns strictly more than `1 + epsilon`,
+/// computed exactly from the raw price strings.
+/// Fails when a price or fee cannot be read as a decimal.
+pub fn exceeds_threshold(graph: &ArbGraph, edges: &[EdgeIndex], epsilon: f64) -> Result<bool, String> {
+    let epsilon = parse_decimal(&epsilon.to_string()).map_err(|e| format!("epsilon: {}", e))?;
+    let mut product = one();
+    for &edge in edges {
+        product *= exact_rate(&graph[edge]).map_err(|e| format!("{}: {}", graph[edge], e))?;
+    }
+    Ok(product > one() + epsilon)
+}
+
+/// Keeps the cycles that clear `1 + epsilon` exactly, dropping the rest with
+/// a message for any that could not be checked. Returns how many were dropped.
+pub fn retain_exact<T>(graph: &ArbGraph, cycles: &mut Vec<T>, epsilon: f64, edges: impl Fn(&T) -> &[EdgeIndex]) -> usize {
+    let before = cycles.len();
+    cycles.retain(|cycle| match exceeds_threshold(graph, edges(cycle), epsilon) {
+        Ok(profitable) => profitable,
+        Err(e) => {
+            println!("Cannot verify cycle exactly: {}", e);
+            false
+        }
+    });
+    before - cycles.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::fixtures::{edge, graph};
+
+    fn ratio(numerator: i64, denominator: i64) -> BigRational {
+        BigRational::new(BigInt::from(numerator), BigInt::from(denominator))
+    }
+
+    #[test]
+    fn parses_plain_and_scientific_decimals() {
+        assert_eq!(parse_decimal("0.05759"), Ok(ratio(5759, 100_000)));
+        assert_eq!(parse_decimal("1e-8"), Ok(ratio(1, 100_000_000)));
+        assert_eq!(parse_decimal("5E+3"), Ok(ratio(5000, 1)));
+        assert_eq!(parse_decimal("1.5e2"), Ok(ratio(150, 1)));
+        assert_eq!(parse_decimal("+.25"), Ok(ratio(1, 4)));
+        assert_eq!(parse_decimal("000.000"), Ok(ratio(0, 1)));
+        for bad in ["", ".", "-1", "1e", "abc", "1.2.3"] {
+            assert!(parse_decimal(bad).is_err(), "{:?} should not parse", bad);
+        }
+    }
+
+    #[test]
+    fn exactly_one_is_not_a_profit() {
+        // 0.1 × 0.2 ÷ 0.02 is exactly 1, but 1.0000000000000002 multiplied in floats.
+        let mut g = graph(3, &[(0, 1, 0.1), (1, 2, 0.2), (2, 0, 0.02)], &[]);
+        let edges: Vec<_> = g.edge_indices().collect();
+        g[edges[2]].side = Side::Buy;
+        assert!(edges.iter().map(|&e| g[e].rate()).product::<f64>() > 1.0);
+        assert_eq!(exceeds_threshold(&g, &edges, 0.0), Ok(false));
+
+        g[edges[2]].raw_price = "0.019999".to_string();
+        assert_eq!(exceeds_threshold(&g, &edges, 0.0), Ok(true));
+        assert_eq!(exceeds_threshold(&g, &edges, 0.0001), Ok(false));
+    }
+
+    #[test]
+    fn fees_and_bad_prices() {
+        let mut g = graph(2, &[(0, 1, 2.0), (1, 0, 0.5)], &[]);
+        let edges: Vec<_> = g.edge_indices().collect();
+        g[edges[0]].raw_price = "2.002".to_string();
+        assert_eq!(exceeds_threshold(&g, &edges, 0.0), Ok(true));
+        g[edges[0]].fee = 0.001;
+        assert_eq!(exceeds_threshold(&g, &edges, 0.0), Ok(false));
+
+        g[edges[1]].raw_price = "n/a".to_string();
+        assert!(exceeds_threshold(&g, &edges, 0.0).is_err());
+        let mut cycles = vec![edges.clone(), vec![edges[0]]];
+        assert_eq!(retain_exact(&g, &mut cycles, 0.0, |c| c), 1);
+        assert_eq!(cycles, vec![vec![edges[0]]]);
+
+        let buy = Edge { side: Side::Buy, raw_price: "0".to_string(), ..edge(1.0) };
+        assert!(exact_rate(&buy).is_err());
+        let transfer = Edge { side: Side::Transfer, raw_price: "1".to_string(), fee: 0.25, ..edge(1.0) };
+        assert_eq!(exact_rate(&transfer), Ok(ratio(3, 4)));
+    }
+}
@@ -0,0 +1,144 @@
+//! Several venues in one graph, joined by transfer edges.
+//!
+//! Nodes are `exchange:asset` labels. Trade edges come from each exchange's
+//! price file; every asset listed on two exchanges also gets a transfer edge in
+//! each direction, priced by its withdrawal fee and a delay penalty. Transfer
+//! costs are loaded from a JSON file such as:
+//!
+//! ```json
+//! {
+//!   "default_withdrawal_fee": 0.001,
+//!   "withdrawal_fees": { "BTC": 0.0005, "USDT": 0.0002 },
+//!   "delay_penalty": 0.0005
+//! }
+//! ```
+
+use crate::edge::{Edge, Side};
+use crate::exchange_info::RulesMap;
+use crate::fees::FeeSchedule;
+use crate::graph::{node_label, ArbGraph, GraphBuilder, ParallelEdges};
+use crate::ingest::{IngestionReport, Quote, TickerMapping};
+use petgraph::graph::NodeIndex;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// A price file tagged with the exchange it was taken from, written `name=path`.
+#[derive(Clone, Debug)]
+pub struct ExchangeInput {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl FromStr for ExchangeInput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((name, path)) if !name.is_empty() && !name.contains(':') && !path.is_empty() => {
+                Ok(ExchangeInput { name: name.to_string(), path: PathBuf::from(path) })
+            }
+            _ => Err(format!("expected NAME=PATH with a name free of ':', got '{}'", s)),
+        }
+    }
+}
+
+/// Cost of moving an asset between exchanges, as fractions of the amount moved.
+/// Exchanges charge flat withdrawal fees, so a fraction is an approximation
+/// that only holds near the transfer size it was derived from.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransferConfig {
+    #[serde(default)]
+    pub default_withdrawal_fee: f64,
+    #[serde(default)]
+    pub withdrawal_fees: HashMap<String, f64>,
+    /// Charged on every transfer for the price risk while funds are in flight.
+    #[serde(default)]
+    pub delay_penalty: f64,
+}
+
+impl TransferConfig {
+    /// Reads transfer costs from a JSON file, rejecting fractions outside `[0, 1)`.
+    pub fn load(path: &Path) -> Result<TransferConfig, Box<dyn Error>> {
+        let data = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        let config: TransferConfig = serde_json::from_str(&data)?;
+        let costs = config
+            .withdrawal_fees
+            .iter()
+            .map(|(asset, fee)| (asset.as_str(), *fee))
+            .chain([("default_withdrawal_fee", config.default_withdrawal_fee), ("delay_penalty", config.delay_penalty)]);
+        for (name, cost) in costs {
+            if !(0.0..1.0).contains(&cost) {
+                return Err(format!("{}: {} cost {} is outside [0, 1)", path.display(), name, cost).into());
+            }
+        }
+        Ok(config)
+    }
+
+    /// Fraction of `asset` lost moving it from one exchange to another.
+    pub fn fee(&self, asset: &str) -> f64 {
+        let fee = self.withdrawal_fees.get(asset).copied().unwrap_or(self.default_withdrawal_fee);
+        1.0 - (1.0 - fee) * (1.0 - self.delay_penalty)
+    }
+}
+
+/// Builds one graph over every exchange's quotes plus transfer edges between
+/// the copies of each asset. Reported symbols are prefixed by their exchange.
+/// Exchange names must be distinct, or their nodes merge. `rules` bound the
+/// trade edges of every exchange.
+pub fn multi_exchange_graph(
+    ticker_mapping: &TickerMapping,
+    rules: RulesMap,
+    exchanges: Vec<(String, Vec<Quote>)>,
+    fees: &FeeSchedule,
+    transfers: &TransferConfig,
+    policy: ParallelEdges,
+) -> (ArbGraph, HashMap<String, NodeIndex>, IngestionReport) {
+    let mut builder = GraphBuilder::with_rules(policy, rules);
+    let names: Vec<String> = exchanges.iter().map(|(name, _)| name.clone()).collect();
+    for (name, quotes) in exchanges {
+        builder.add_quotes(ticker_mapping, quotes, fees, Some(&name));
+    }
+
+    // Group nodes by asset; a BTreeMap keeps edge insertion deterministic.
+    let mut venues: BTreeMap<String, Vec<(&str, NodeIndex)>> = BTreeMap::new();
+    for name in &names {
+        let prefix = node_label(Some(name), "");
+        for (label, &node) in &builder.nodes {
+            if let Some(asset) = label.strip_prefix(&prefix) {
+                venues.entry(asset.to_string()).or_default().push((name, node));
+            }
+        }
+    }
+    let mut transfer_edges = 0;
+    for (asset, nodes) in &venues {
+        for &(exchange, from) in nodes {
+            for &(_, to) in nodes {
+                if from != to {
+                    let edge = Edge {
+                        symbol: asset.clone(),
+                        side: Side::Transfer,
+                        price: 1.0,
+                        raw_price: "1".to_string(),
+                        fee: transfers.fee(asset),
+                        min_qty: None,
+                        max_qty: None,
+                        top_qty: None,
+                        source: exchange.to_string(),
+                        timestamp: None,
+                        consumed: false,
+                    };
+                    builder.graph.add_edge(from, to, edge);
+                    transfer_edges += 1;
+                }
+            }
+        }
+    }
+    println!("Added {} transfer edges across {} exchanges.", transfer_edges, names.len());
+    builder.finish()
+}
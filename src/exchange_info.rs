@@ -0,0 +1,230 @@
+//! Binance `exchangeInfo` metadata: the symbol mapping and per-symbol trading rules.
+//!
+//! Only `TRADING` symbols are kept, so halted markets never produce edges.
+//! Fields and filter types this crate does not use are ignored.
+
+use crate::edge::{Edge, Side};
+use crate::ingest::{SymbolMapping, TickerMapping};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+pub struct ExchangeInfo {
+    pub symbols: Vec<SymbolInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolInfo {
+    pub symbol: String,
+    #[serde(default)]
+    pub status: Option<String>,
+    pub base_asset: String,
+    pub quote_asset: String,
+    #[serde(default)]
+    pub filters: Vec<SymbolFilter>,
+}
+
+/// The filters that constrain an order's price, quantity and value.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "filterType", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SymbolFilter {
+    #[serde(rename_all = "camelCase")]
+    PriceFilter { tick_size: String },
+    #[serde(rename_all = "camelCase")]
+    LotSize { min_qty: String, max_qty: String, step_size: String },
+    #[serde(rename_all = "camelCase")]
+    MinNotional { min_notional: String },
+    #[serde(rename_all = "camelCase")]
+    Notional { min_notional: String },
+    #[serde(other)]
+    Other,
+}
+
+/// Parsed trading rules of one symbol. Zero means unconstrained.
+#[derive(Clone, Debug, Default)]
+pub struct SymbolRules {
+    pub tick_size: f64,
+    pub step_size: f64,
+    pub min_qty: f64,
+    pub max_qty: f64,
+    /// Smallest order value, in the quote currency.
+    pub min_notional: f64,
+}
+
+pub type RulesMap = HashMap<String, SymbolRules>;
+
+/// Rounds `value` down to a multiple of `step`, leaving it alone when `step` is zero.
+fn round_down(value: f64, step: f64) -> f64 {
+    if step > 0.0 {
+        (value / step + 1e-9).floor() * step
+    } else {
+        value
+    }
+}
+
+/// Rounds `value` up to a multiple of `step`, leaving it alone when `step` is zero.
+fn round_up(value: f64, step: f64) -> f64 {
+    if step > 0.0 {
+        (value / step - 1e-9).ceil() * step
+    } else {
+        value
+    }
+}
+
+fn parse_rule(symbol: &str, name: &str, value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(v) if v.is_finite() && v >= 0.0 => Ok(v),
+        _ => Err(format!("{}: invalid {} '{}'", symbol, name, value)),
+    }
+}
+
+impl SymbolRules {
+    fn from_filters(symbol: &str, filters: &[SymbolFilter]) -> Result<SymbolRules, String> {
+        let mut rules = SymbolRules::default();
+        for filter in filters {
+            match filter {
+                SymbolFilter::PriceFilter { tick_size } => rules.tick_size = parse_rule(symbol, "tickSize", tick_size)?,
+                SymbolFilter::LotSize { min_qty, max_qty, step_size } => {
+                    rules.min_qty = parse_rule(symbol, "minQty", min_qty)?;
+                    rules.max_qty = parse_rule(symbol, "maxQty", max_qty)?;
+                    rules.step_size = parse_rule(symbol, "stepSize", step_size)?;
+                }
+                SymbolFilter::MinNotional { min_notional } | SymbolFilter::Notional { min_notional } => {
+                    rules.min_notional = parse_rule(symbol, "minNotional", min_notional)?;
+                }
+                SymbolFilter::Other => {}
+            }
+        }
+        Ok(rules)
+    }
+}
+
+/// Reads an exchangeInfo document into a ticker mapping and each symbol's rules.
+/// Base assets map to `base` and quote assets to `other`.
+pub fn load_exchange_info(path: &Path) -> Result<(TickerMapping, RulesMap), Box<dyn Error>> {
+    let data = fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+    let info: ExchangeInfo = serde_json::from_str(&data)?;
+
+    let mut mapping = TickerMapping::new();
+    let mut rules = RulesMap::new();
+    for symbol in info.symbols {
+        if symbol.status.as_deref().is_some_and(|s| s != "TRADING") {
+            continue;
+        }
+        let parsed = SymbolRules::from_filters(&symbol.symbol, &symbol.filters)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        rules.insert(symbol.symbol.clone(), parsed);
+        mapping.insert(symbol.symbol, SymbolMapping { base: symbol.base_asset, other: symbol.quote_asset });
+    }
+    Ok((mapping, rules))
+}
+
+/// Why a cycle cannot be executed once orders respect the symbol rules.
+#[derive(Debug)]
+pub enum FilterRejection {
+    /// The rounded quantity is below `LOT_SIZE` `minQty`.
+    BelowMinQty { symbol: String, qty: f64 },
+    /// The order value is below `MIN_NOTIONAL`.
+    BelowMinNotional { symbol: String, notional: f64 },
+}
+
+impl fmt::Display for FilterRejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FilterRejection::BelowMinQty { symbol, qty } => write!(f, "{} quantity {} below minimum", symbol, qty),
+            FilterRejection::BelowMinNotional { symbol, notional } => {
+                write!(f, "{} notional {} below minimum", symbol, notional)
+            }
+        }
+    }
+}
+
+/// Simulates orders at the prices a cycle was detected on, rounded to tick
+/// size, with quantities rounded down to step size and capped at `maxQty`.
+pub struct FilterSimulator {
+    rules: RulesMap,
+}
+
+impl FilterSimulator {
+    pub fn new(rules: RulesMap) -> FilterSimulator {
+        FilterSimulator { rules }
+    }
+
+    /// Amount of the first currency left after pushing `amount` through the
+    /// cycle's `legs`, each traded on its own symbol, side and price.
+    /// Quantity lost to rounding stays behind as dust and is not counted.
+    pub fn execute(&self, legs: &[Edge], amount: f64) -> Result<f64, FilterRejection> {
+        let mut held = amount;
+        for leg in legs {
+            if leg.side == Side::Transfer {
+                held *= 1.0 - leg.fee;
+                continue;
+            }
+            let rules = self.rules.get(&leg.symbol).cloned().unwrap_or_default();
+            // A sell limit may not rest above the bid, nor a buy limit below the ask.
+            let (price, wanted) = match leg.side {
+                Side::Sell => (round_down(leg.price, rules.tick_size), held),
+                Side::Buy | Side::Transfer => {
+                    let price = round_up(leg.price, rules.tick_size);
+                    (price, held / price)
+                }
+            };
+            let mut qty = round_down(wanted, rules.step_size);
+            if rules.max_qty > 0.0 {
+                qty = qty.min(rules.max_qty);
+            }
+            if qty <= 0.0 || qty < rules.min_qty {
+                return Err(FilterRejection::BelowMinQty { symbol: leg.symbol.clone(), qty });
+            }
+            let notional = qty * price;
+            if notional < rules.min_notional {
+                return Err(FilterRejection::BelowMinNotional { symbol: leg.symbol.clone(), notional });
+            }
+            held = match leg.side {
+                Side::Sell => notional,
+                Side::Buy | Side::Transfer => qty,
+            } * (1.0 - leg.fee);
+        }
+        Ok(held)
+    }
+
+    /// Return of the cycle at `amount` after rounding, or why it cannot be traded.
+    pub fn rounded_profit(&self, legs: &[Edge], amount: f64) -> Result<f64, FilterRejection> {
+        self.execute(legs, amount).map(|out| out / amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::fixtures::edge;
+
+    fn leg(symbol: &str, side: Side, price: f64) -> Edge {
+        Edge { symbol: symbol.to_string(), side, price, ..edge(1.0) }
+    }
+
+    #[test]
+    fn rounds_each_leg_on_its_own_symbol() {
+        let mut rules = RulesMap::new();
+        rules.insert(
+            "AB".to_string(),
+            SymbolRules { tick_size: 0.01, step_size: 0.1, min_qty: 0.1, max_qty: 0.0, min_notional: 1.0 },
+        );
+        let simulator = FilterSimulator::new(rules);
+        // Sell 2.05 A at 1.104 -> 2.0 A at 1.10; transfer B at 10%; buy A at 1.0.
+        let legs = [
+            leg("AB", Side::Sell, 1.104),
+            Edge { fee: 0.1, ..leg("B", Side::Transfer, 1.0) },
+            leg("AB", Side::Buy, 1.0),
+        ];
+        let out = simulator.execute(&legs, 2.05).unwrap();
+        assert!((out - 1.9).abs() < 1e-9, "{}", out);
+        assert!(matches!(simulator.execute(&legs, 0.5), Err(FilterRejection::BelowMinNotional { .. })));
+        assert!(matches!(simulator.execute(&legs, 0.05), Err(FilterRejection::BelowMinQty { .. })));
+    }
+}
@@ -0,0 +1,73 @@
+//! Writing graphs and cycle lists to disk.
+
+use crate::backtest::{OpportunityWindow, TickSummary};
+use crate::detect::enumerate::ArbitrageCycle;
+use crate::graph::ArbGraph;
+use petgraph::dot::Dot;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Writes the current graph in DOT format into `dot_dir`.
+pub fn output_dot_file(graph: &ArbGraph, dot_dir: &Path, dot_filename: &str) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(dot_dir)?;
+    let dot_path = dot_dir.join(dot_filename);
+    let dot_str = format!("{}", Dot::new(graph));
+    let mut file = fs::File::create(dot_path)?;
+    file.write_all(dot_str.as_bytes())?;
+    Ok(())
+}
+
+/// Writes ranked cycles as CSV, one row per cycle.
+pub fn output_cycles_csv(cycles: &[ArbitrageCycle], csv_path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut wtr = csv::Writer::from_path(csv_path)?;
+    wtr.write_record(["rank", "legs", "profit", "geometric_mean", "path"])?;
+    for (rank, cycle) in cycles.iter().enumerate() {
+        wtr.write_record(&[
+            (rank + 1).to_string(),
+            cycle.nodes.len().to_string(),
+            cycle.rate_product.to_string(),
+            cycle.geometric_mean_return().to_string(),
+            cycle.currencies.join(" -> "),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Writes the backtest time series as CSV, one row per snapshot.
+pub fn output_backtest_csv(ticks: &[TickSummary], csv_path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut wtr = csv::Writer::from_path(csv_path)?;
+    wtr.write_record(["tick", "timestamp", "cycles", "best_return", "best_persistence", "max_persistence", "best_path"])?;
+    for (tick, summary) in ticks.iter().enumerate() {
+        wtr.write_record(&[
+            tick.to_string(),
+            summary.timestamp.clone(),
+            summary.cycles.to_string(),
+            summary.best_return.to_string(),
+            summary.best_persistence.to_string(),
+            summary.max_persistence.to_string(),
+            summary.best_cycle.join(" -> "),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Writes opportunity windows as CSV, longest first.
+pub fn output_windows_csv(windows: &[OpportunityWindow], csv_path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut wtr = csv::Writer::from_path(csv_path)?;
+    wtr.write_record(["opened", "closed", "ticks", "best_return", "path"])?;
+    for window in windows {
+        wtr.write_record(&[
+            window.opened.clone(),
+            window.closed.clone(),
+            window.ticks.to_string(),
+            window.best_return.to_string(),
+            window.cycle.join(" -> "),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
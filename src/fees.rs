@@ -0,0 +1,124 @@
+//! Trading fee schedules folded into the graph's edge weights.
+//!
+//! A schedule is loaded from a JSON file such as:
+//!
+//! ```json
+//! {
+//!   "maker": 0.001,
+//!   "taker": 0.001,
+//!   "tier": "taker",
+//!   "symbols": { "BTCUSDT": { "taker": 0.0 } },
+//!   "discount": { "currency": "BNB", "rate": 0.25 },
+//!   "fee_currency": "BNB"
+//! }
+//! ```
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Which side of the schedule applies to every fill.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeeTier {
+    /// Resting orders that add liquidity.
+    Maker,
+    /// Orders that cross the spread; what an arbitrage loop normally pays.
+    #[default]
+    Taker,
+}
+
+/// Per-symbol fee override; missing fields fall back to the schedule's defaults.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SymbolFee {
+    pub maker: Option<f64>,
+    pub taker: Option<f64>,
+}
+
+/// Discount granted when fees are paid in a particular currency, e.g. 25% off in BNB.
+/// Applies only if `FeeSchedule::fee_currency` names that currency.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeeDiscount {
+    pub currency: String,
+    /// Fraction taken off every fee.
+    pub rate: f64,
+}
+
+/// Fees charged on each fill, as fractions of the traded amount.
+/// The default schedule charges nothing.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeeSchedule {
+    #[serde(default)]
+    pub maker: f64,
+    #[serde(default)]
+    pub taker: f64,
+    #[serde(default)]
+    pub tier: FeeTier,
+    #[serde(default)]
+    pub symbols: HashMap<String, SymbolFee>,
+    #[serde(default)]
+    pub discount: Option<FeeDiscount>,
+    /// Currency fees are paid in; the discount applies only when it matches.
+    /// Fees come out of the traded assets when unset.
+    #[serde(default)]
+    pub fee_currency: Option<String>,
+}
+
+impl FeeSchedule {
+    /// Reads a fee schedule from a JSON file, rejecting fees outside `[0, 1)`.
+    pub fn load(path: &Path) -> Result<FeeSchedule, Box<dyn Error>> {
+        let data = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        let schedule: FeeSchedule = serde_json::from_str(&data)?;
+
+        let mut fees = vec![("maker".to_string(), schedule.maker), ("taker".to_string(), schedule.taker)];
+        for (symbol, fee) in &schedule.symbols {
+            fees.extend(fee.maker.map(|f| (format!("{} maker", symbol), f)));
+            fees.extend(fee.taker.map(|f| (format!("{} taker", symbol), f)));
+        }
+        fees.extend(schedule.discount.as_ref().map(|d| ("discount".to_string(), d.rate)));
+        for (name, fee) in fees {
+            if !(0.0..1.0).contains(&fee) {
+                return Err(format!("{}: {} fee {} is outside [0, 1)", path.display(), name, fee).into());
+            }
+        }
+        Ok(schedule)
+    }
+
+    /// Fraction of the traded amount charged on a fill of `symbol`.
+    pub fn rate(&self, symbol: &str) -> f64 {
+        let symbol_fee = self.symbols.get(symbol);
+        let fee = match self.tier {
+            FeeTier::Maker => symbol_fee.and_then(|f| f.maker).unwrap_or(self.maker),
+            FeeTier::Taker => symbol_fee.and_then(|f| f.taker).unwrap_or(self.taker),
+        };
+        match &self.discount {
+            Some(discount) if self.fee_currency.as_ref() == Some(&discount.currency) => fee * (1.0 - discount.rate),
+            _ => fee,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discount_applies_only_in_its_currency() {
+        let mut schedule = FeeSchedule {
+            taker: 0.001,
+            discount: Some(FeeDiscount { currency: "BNB".to_string(), rate: 0.25 }),
+            ..FeeSchedule::default()
+        };
+        assert_eq!(schedule.rate("ETHBTC"), 0.001);
+        schedule.fee_currency = Some("USDT".to_string());
+        assert_eq!(schedule.rate("ETHBTC"), 0.001);
+        schedule.fee_currency = Some("BNB".to_string());
+        assert!((schedule.rate("ETHBTC") - 0.00075).abs() < 1e-15);
+    }
+}
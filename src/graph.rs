@@ -0,0 +1,406 @@
+//! The arbitrage graph and helpers for reading cycles back out of it.
+
+use crate::edge::{Edge, Side};
+use crate::exchange_info::{RulesMap, SymbolRules};
+use crate::fees::FeeSchedule;
+use crate::ingest::{IngestionReport, Quote, RejectedPrice, TickerMapping};
+use clap::ValueEnum;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::stable_graph::StableDiGraph;
+use petgraph::visit::EdgeRef;
+use std::collections::{HashMap, HashSet};
+
+/// Arbitrage graph: currencies as nodes, trades and transfers as edges whose
+/// `Edge::log_weight` is `-log2(rate)`.
+/// Stable indices keep `NodeIndex` values valid while nodes are removed.
+pub type ArbGraph = StableDiGraph<String, Edge>;
+
+/// Checks that a parsed price can be turned into a log weight.
+fn price_problem(price: f64) -> Option<&'static str> {
+    if !price.is_finite() {
+        Some("not finite")
+    } else if price <= 0.0 {
+        Some("not positive")
+    } else {
+        None
+    }
+}
+
+/// Checks that a bid and ask can be turned into a pair of edges.
+pub(crate) fn quote_problem(bid: f64, ask: f64) -> Option<&'static str> {
+    price_problem(bid)
+        .or_else(|| price_problem(ask))
+        .or((bid > ask).then_some("bid above ask"))
+}
+
+/// The `base -> other` sell and `other -> base` buy edges of a quote whose
+/// `bid` and `ask` have been parsed and checked, charging `fee` on both.
+/// Quantity bounds come from the symbol's lot size rules, when known, and the
+/// quoted sizes from the quote's top of book.
+pub(crate) fn pair_edges(quote: &Quote, bid: f64, ask: f64, fee: f64, rules: Option<&SymbolRules>) -> (Edge, Edge) {
+    let edge = |side, price, raw_price: &str, qty: &Option<String>| Edge {
+        symbol: quote.symbol.clone(),
+        side,
+        price,
+        raw_price: raw_price.to_string(),
+        fee,
+        min_qty: rules.map(|r| r.min_qty).filter(|&q| q > 0.0),
+        max_qty: rules.map(|r| r.max_qty).filter(|&q| q > 0.0),
+        top_qty: qty.as_deref().and_then(|q| q.parse::<f64>().ok()),
+        source: quote.source.clone(),
+        timestamp: quote.timestamp.clone(),
+        consumed: false,
+    };
+    (
+        edge(Side::Sell, bid, &quote.bid, &quote.bid_qty),
+        edge(Side::Buy, ask, &quote.ask, &quote.ask_qty),
+    )
+}
+
+/// Node label of `asset`, qualified by its exchange when there is one.
+pub fn node_label(exchange: Option<&str>, asset: &str) -> String {
+    match exchange {
+        Some(exchange) => format!("{}:{}", exchange, asset),
+        None => asset.to_string(),
+    }
+}
+
+/// What to do when two symbols trading the same currencies add an edge
+/// between them. A symbol quoted again always replaces its own earlier edges,
+/// so it never trades against a stale copy of itself.
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub enum ParallelEdges {
+    /// Keep one edge per direction carrying the best rate seen.
+    KeepBest,
+    /// Keep one edge per direction carrying the latest rate seen.
+    KeepLatest,
+    /// Keep one edge pair per symbol, as a multigraph; `GraphBuilder::pairs` labels each with its symbol.
+    #[default]
+    KeepAll,
+}
+
+/// Accumulates quotes, possibly from several exchanges, into one graph.
+#[derive(Default)]
+pub struct GraphBuilder {
+    pub policy: ParallelEdges,
+    /// Lot size rules by symbol, giving edges their quantity bounds.
+    pub rules: RulesMap,
+    pub graph: ArbGraph,
+    /// Node of every currency label added so far.
+    pub nodes: HashMap<String, NodeIndex>,
+    pub report: IngestionReport,
+    /// `base -> other` and `other -> base` edges of every symbol added, the
+    /// latest one when a symbol repeats. Under `KeepBest` and `KeepLatest`
+    /// symbols trading the same pair share edges.
+    pub pairs: HashMap<String, (EdgeIndex, EdgeIndex)>,
+    seen: HashSet<String>,
+}
+
+impl GraphBuilder {
+    pub fn new(policy: ParallelEdges) -> GraphBuilder {
+        GraphBuilder { policy, ..GraphBuilder::default() }
+    }
+
+    /// A builder whose edges take their quantity bounds from `rules`.
+    pub fn with_rules(policy: ParallelEdges, rules: RulesMap) -> GraphBuilder {
+        GraphBuilder { policy, rules, ..GraphBuilder::default() }
+    }
+
+    /// Returns the node for `label`, adding it on first use.
+    pub fn node(&mut self, label: String) -> NodeIndex {
+        let graph = &mut self.graph;
+        *self.nodes.entry(label.clone()).or_insert_with(|| graph.add_node(label))
+    }
+
+    /// Adds an edge, or folds it into an existing one unless the policy keeps all.
+    /// `previous` is the edge the same symbol added earlier, which the new one
+    /// replaces while it still holds that symbol.
+    fn add_edge(&mut self, source: NodeIndex, target: NodeIndex, edge: Edge, previous: Option<EdgeIndex>) -> EdgeIndex {
+        if let Some(index) = previous.filter(|&index| self.graph[index].symbol == edge.symbol) {
+            self.graph[index] = edge;
+            return index;
+        }
+        let existing = match self.policy {
+            ParallelEdges::KeepAll => None,
+            ParallelEdges::KeepBest | ParallelEdges::KeepLatest => self.graph.find_edge(source, target),
+        };
+        match existing {
+            Some(index) => {
+                let keep_new = match self.policy {
+                    ParallelEdges::KeepBest => edge.log_weight() < self.graph[index].log_weight(),
+                    _ => true,
+                };
+                if keep_new {
+                    self.graph[index] = edge;
+                }
+                index
+            }
+            None => self.graph.add_edge(source, target, edge),
+        }
+    }
+
+    /// Adds a pair of edges per quote, reporting every quote it skipped.
+    /// Selling the base fills at the bid, so `base -> other` weighs `-log2(bid)`;
+    /// buying it back costs the ask, so `other -> base` weighs `log2(ask)`.
+    /// Both directions also carry the symbol's fee, so cycle profits are net of fees.
+    /// With an `exchange`, nodes and reported symbols are prefixed by its name
+    /// and edges record it as their source.
+    pub fn add_quotes(
+        &mut self,
+        ticker_mapping: &TickerMapping,
+        json: Vec<Quote>,
+        fees: &FeeSchedule,
+        exchange: Option<&str>,
+    ) {
+        self.report.ticks += json.len();
+        for entry in json {
+            let symbol = node_label(exchange, &entry.symbol);
+            if !self.seen.insert(symbol.clone()) && !self.report.duplicate_symbols.contains(&symbol) {
+                self.report.duplicate_symbols.push(symbol.clone());
+            }
+            let Some(mapping) = ticker_mapping.get(&entry.symbol) else {
+                self.report.unmapped_symbols.push(symbol);
+                continue;
+            };
+            let (bid, ask) = match (entry.bid.parse::<f64>(), entry.ask.parse::<f64>()) {
+                (Ok(bid), Ok(ask)) => (bid, ask),
+                (Err(e), _) | (_, Err(e)) => {
+                    self.report.rejected_prices.push(RejectedPrice {
+                        price: entry.display_price(),
+                        symbol,
+                        reason: format!("unparsable: {}", e),
+                    });
+                    continue;
+                }
+            };
+
+            let node_a = self.node(node_label(exchange, &mapping.base));
+            let node_b = self.node(node_label(exchange, &mapping.other));
+
+            if let Some(reason) = quote_problem(bid, ask) {
+                self.report.rejected_prices.push(RejectedPrice {
+                    price: entry.display_price(),
+                    symbol,
+                    reason: reason.to_string(),
+                });
+            } else {
+                let rules = self.rules.get(&entry.symbol);
+                let (mut sell, mut buy) = pair_edges(&entry, bid, ask, fees.rate(&entry.symbol), rules);
+                if let Some(exchange) = exchange {
+                    sell.source = exchange.to_string();
+                    buy.source = exchange.to_string();
+                }
+                let previous = self.pairs.get(&symbol).copied();
+                let forward = self.add_edge(node_a, node_b, sell, previous.map(|(forward, _)| forward));
+                let reverse = self.add_edge(node_b, node_a, buy, previous.map(|(_, reverse)| reverse));
+                self.pairs.insert(symbol, (forward, reverse));
+                self.report.pairs_added += 1;
+            }
+        }
+    }
+
+    /// Lists isolated nodes in the report and returns the finished graph.
+    pub fn finish(mut self) -> (ArbGraph, HashMap<String, NodeIndex>, IngestionReport) {
+        let graph = &self.graph;
+        self.report.isolated_nodes = graph
+            .node_indices()
+            .filter(|&n| graph.neighbors_undirected(n).next().is_none())
+            .map(|n| graph[n].clone())
+            .collect();
+        (self.graph, self.nodes, self.report)
+    }
+}
+
+/// Builds a directed graph from the provided quotes, reporting every one it skipped.
+/// See `GraphBuilder::add_quotes` for how quotes become edge weights, and
+/// `GraphBuilder::with_rules` for edges bounded by symbol rules.
+pub fn graph_builder(
+    ticker_mapping: TickerMapping,
+    json: Vec<Quote>,
+    fees: &FeeSchedule,
+    policy: ParallelEdges,
+) -> (ArbGraph, HashMap<String, NodeIndex>, IngestionReport) {
+    let mut builder = GraphBuilder::new(policy);
+    builder.add_quotes(&ticker_mapping, json, fees, None);
+    builder.finish()
+}
+
+/// Computes the average out-degree of nodes in the graph.
+pub fn average_out_degree(graph: &ArbGraph) -> f64 {
+    if graph.node_count() == 0 {
+        return 0.0;
+    }
+    let total: usize = graph.node_indices()
+        .map(|n| graph.neighbors_directed(n, petgraph::Direction::Outgoing).count())
+        .sum();
+    total as f64 / graph.node_count() as f64
+}
+
+/// Returns the cheapest edge weight from `source` to `target`.
+pub fn min_edge_weight(graph: &ArbGraph, source: NodeIndex, target: NodeIndex) -> f64 {
+    graph
+        .edges_connecting(source, target)
+        .map(|edge| edge.weight().log_weight())
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// Returns the lightest edge from `source` to `target`, the one Bellman-Ford relaxes through.
+pub fn lightest_edge(graph: &ArbGraph, source: NodeIndex, target: NodeIndex) -> Option<EdgeIndex> {
+    graph
+        .edges_connecting(source, target)
+        .min_by(|a, b| a.weight().log_weight().total_cmp(&b.weight().log_weight()))
+        .map(|edge| edge.id())
+}
+
+/// Returns the edges traversed by a cycle given in traversal order, closing edge last.
+pub fn cycle_edges(graph: &ArbGraph, cycle: &[NodeIndex]) -> Vec<EdgeIndex> {
+    cycle
+        .iter()
+        .zip(cycle.iter().cycle().skip(1))
+        .filter_map(|(&source, &target)| lightest_edge(graph, source, target))
+        .collect()
+}
+
+/// Returns the nodes a cycle given as edges visits, each edge's source in order.
+pub fn cycle_nodes(graph: &ArbGraph, edges: &[EdgeIndex]) -> Vec<NodeIndex> {
+    edges.iter().filter_map(|&edge| graph.edge_endpoints(edge)).map(|(source, _)| source).collect()
+}
+
+/// Product of rates around a cycle, computed from the log weights of the
+/// exact edges it traverses.
+pub fn cycle_profit(graph: &ArbGraph, edges: &[EdgeIndex]) -> f64 {
+    edges.iter().map(|&edge| 2f64.powf(-graph[edge].log_weight())).product()
+}
+
+/// Hand-built graphs for unit tests.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::ArbGraph;
+    use crate::edge::{Edge, Side};
+    use crate::ingest::{Quote, SymbolMapping, TickerMapping};
+    use petgraph::graph::NodeIndex;
+
+    /// Mapping from `(symbol, base, other)` triples.
+    pub(crate) fn mapping(symbols: &[(&str, &str, &str)]) -> TickerMapping {
+        symbols
+            .iter()
+            .map(|&(symbol, base, other)| {
+                (symbol.to_string(), SymbolMapping { base: base.to_string(), other: other.to_string() })
+            })
+            .collect()
+    }
+
+    /// A quote of `symbol` at `bid` and `ask`.
+    pub(crate) fn quote(symbol: &str, bid: &str, ask: &str) -> Quote {
+        Quote {
+            symbol: symbol.to_string(),
+            bid: bid.to_string(),
+            ask: ask.to_string(),
+            bid_qty: None,
+            ask_qty: None,
+            source: "test".to_string(),
+            timestamp: None,
+        }
+    }
+
+    /// A fee-free edge converting one unit of its source into `rate` of its target.
+    pub(crate) fn edge(rate: f64) -> Edge {
+        Edge {
+            symbol: format!("X{}", rate),
+            side: Side::Sell,
+            price: rate,
+            raw_price: rate.to_string(),
+            fee: 0.0,
+            min_qty: None,
+            max_qty: None,
+            top_qty: None,
+            source: "test".to_string(),
+            timestamp: None,
+            consumed: false,
+        }
+    }
+
+    /// Graph over nodes labelled `A`, `B`, ... with `(source, target, rate)`
+    /// edges. The `removed` nodes are deleted afterwards, leaving holes in the
+    /// index space as the removal loop does.
+    pub(crate) fn graph(nodes: usize, edges: &[(usize, usize, f64)], removed: &[usize]) -> ArbGraph {
+        let mut graph = ArbGraph::default();
+        for i in 0..nodes {
+            graph.add_node(((b'A' + i as u8) as char).to_string());
+        }
+        for &(source, target, rate) in edges {
+            graph.add_edge(NodeIndex::new(source), NodeIndex::new(target), edge(rate));
+        }
+        for &node in removed {
+            graph.remove_node(NodeIndex::new(node));
+        }
+        graph
+    }
+
+    /// Labels of the nodes a cycle visits.
+    pub(crate) fn labels(graph: &ArbGraph, nodes: &[NodeIndex]) -> Vec<String> {
+        nodes.iter().map(|&n| graph[n].clone()).collect()
+    }
+
+    /// Asserts that `edges` chain head to tail and close back on the first node.
+    pub(crate) fn assert_closed(graph: &ArbGraph, edges: &[petgraph::graph::EdgeIndex]) {
+        assert!(!edges.is_empty());
+        for (i, &edge) in edges.iter().enumerate() {
+            let next = edges[(i + 1) % edges.len()];
+            assert_eq!(graph.edge_endpoints(edge).unwrap().1, graph.edge_endpoints(next).unwrap().0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::{mapping, quote};
+    use super::*;
+    use crate::detect::{negative_finder, DetectionMode};
+
+    const POLICIES: [ParallelEdges; 3] = [ParallelEdges::KeepBest, ParallelEdges::KeepLatest, ParallelEdges::KeepAll];
+
+    fn build(policy: ParallelEdges, quotes: Vec<Quote>) -> (ArbGraph, HashMap<String, NodeIndex>, IngestionReport) {
+        let mapping = mapping(&[("ETHBTC", "ETH", "BTC"), ("ETHBTC2", "ETH", "BTC")]);
+        graph_builder(mapping, quotes, &FeeSchedule::default(), policy)
+    }
+
+    /// Bids of the `ETH -> BTC` edges, in index order.
+    fn sell_prices(graph: &ArbGraph, nodes: &HashMap<String, NodeIndex>) -> Vec<String> {
+        let mut edges: Vec<_> = graph.edges_connecting(nodes["ETH"], nodes["BTC"]).collect();
+        edges.sort_by_key(|edge| edge.id());
+        edges.iter().map(|edge| edge.weight().raw_price.clone()).collect()
+    }
+
+    #[test]
+    fn repeated_symbol_replaces_its_own_pair() {
+        for policy in POLICIES {
+            // A stale copy selling far above the fresh ask would be a 2-cycle.
+            let quotes = vec![quote("ETHBTC", "0.06", "0.06"), quote("ETHBTC", "0.05", "0.05")];
+            let (graph, nodes, report) = build(policy, quotes);
+            assert_eq!(graph.edge_count(), 2, "{:?}", policy);
+            assert_eq!(sell_prices(&graph, &nodes), ["0.05"], "{:?}", policy);
+            assert_eq!(report.duplicate_symbols, ["ETHBTC"]);
+            assert!(negative_finder(&graph, DetectionMode::PerScc, 0.0).is_none(), "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn policies_resolve_distinct_symbols_on_one_pair() {
+        let quotes = || vec![quote("ETHBTC", "0.051", "0.052"), quote("ETHBTC2", "0.050", "0.051")];
+
+        let (graph, nodes, _) = build(ParallelEdges::KeepAll, quotes());
+        assert_eq!(graph.edge_count(), 4);
+        assert_eq!(sell_prices(&graph, &nodes), ["0.051", "0.050"]);
+
+        let (graph, nodes, _) = build(ParallelEdges::KeepLatest, quotes());
+        assert_eq!(graph.edge_count(), 2);
+        assert_eq!(sell_prices(&graph, &nodes), ["0.050"]);
+
+        let (graph, nodes, _) = build(ParallelEdges::KeepBest, quotes());
+        assert_eq!(graph.edge_count(), 2);
+        assert_eq!(sell_prices(&graph, &nodes), ["0.051"]);
+        let buy = graph.find_edge(nodes["BTC"], nodes["ETH"]).unwrap();
+        assert_eq!(graph[buy].raw_price, "0.051");
+    }
+}
@@ -0,0 +1,353 @@
+//! Loading and validating the symbol mapping and price snapshots.
+
+use clap::ValueEnum;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Base and quote currency of a trading symbol, as listed in dict.json.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SymbolMapping {
+    /// Currency sold when the symbol is sold.
+    pub base: String,
+    /// Currency received when the symbol is sold.
+    pub other: String,
+}
+
+/// Last traded price of a symbol, as listed in the price files.
+/// The price stays a string until the graph is built.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PriceTick {
+    pub symbol: String,
+    pub price: String,
+}
+
+/// Best bid and ask of a symbol, as served by Binance `bookTicker`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BookTicker {
+    pub symbol: String,
+    pub bid_price: String,
+    pub bid_qty: String,
+    pub ask_price: String,
+    pub ask_qty: String,
+}
+
+/// Layout of the entries in a price file.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum PriceFormat {
+    /// `{"symbol", "price"}` last-trade ticks.
+    Last,
+    /// `{"symbol", "bidPrice", "bidQty", "askPrice", "askQty"}` top-of-book ticks.
+    BookTicker,
+}
+
+/// The rates a symbol can be traded at: selling the base currency fills at
+/// `bid`, buying it fills at `ask`. A last-trade tick uses its price for both.
+#[derive(Clone, Debug)]
+pub struct Quote {
+    pub symbol: String,
+    pub bid: String,
+    pub ask: String,
+    /// Base quantity available at the bid and ask, when the input has it.
+    pub bid_qty: Option<String>,
+    pub ask_qty: Option<String>,
+    /// File the quote was read from.
+    pub source: String,
+    /// When the quote was observed; set for backtest snapshots.
+    pub timestamp: Option<String>,
+}
+
+impl Quote {
+    /// The quoted price as it appeared in the input, `bid/ask` when they differ.
+    pub fn display_price(&self) -> String {
+        if self.bid == self.ask {
+            self.bid.clone()
+        } else {
+            format!("{}/{}", self.bid, self.ask)
+        }
+    }
+}
+
+impl From<PriceTick> for Quote {
+    fn from(tick: PriceTick) -> Self {
+        Quote {
+            symbol: tick.symbol,
+            bid: tick.price.clone(),
+            ask: tick.price,
+            bid_qty: None,
+            ask_qty: None,
+            source: String::new(),
+            timestamp: None,
+        }
+    }
+}
+
+impl From<BookTicker> for Quote {
+    fn from(ticker: BookTicker) -> Self {
+        Quote {
+            symbol: ticker.symbol,
+            bid: ticker.bid_price,
+            ask: ticker.ask_price,
+            bid_qty: Some(ticker.bid_qty),
+            ask_qty: Some(ticker.ask_qty),
+            source: String::new(),
+            timestamp: None,
+        }
+    }
+}
+
+/// Symbol to currency pair lookup loaded from dict.json.
+pub type TickerMapping = HashMap<String, SymbolMapping>;
+
+/// Where a malformed entry sits in its file. Positions sort numerically,
+/// before any name.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EntryKey {
+    /// Array position of a price entry or depth snapshot.
+    Index(usize),
+    /// Symbol of a mapping entry, or the part of a line that failed.
+    Name(String),
+}
+
+impl fmt::Display for EntryKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EntryKey::Index(i) => write!(f, "{}", i),
+            EntryKey::Name(name) => f.write_str(name),
+        }
+    }
+}
+
+/// One input entry that failed validation.
+#[derive(Debug)]
+pub struct MalformedEntry {
+    /// File the entry came from.
+    pub file: String,
+    pub key: EntryKey,
+    pub reason: String,
+}
+
+/// Every malformed entry found while loading the input files.
+#[derive(Debug)]
+pub struct IngestError {
+    pub entries: Vec<MalformedEntry>,
+}
+
+impl fmt::Display for IngestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} malformed input entries:", self.entries.len())?;
+        for entry in &self.entries {
+            writeln!(f, "  {}[{}]: {}", entry.file, entry.key, entry.reason)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for IngestError {}
+
+impl IngestError {
+    /// Lists `entries` by file, then by position or symbol.
+    pub fn new(mut entries: Vec<MalformedEntry>) -> IngestError {
+        entries.sort_by(|a, b| (&a.file, &a.key).cmp(&(&b.file, &b.key)));
+        IngestError { entries }
+    }
+}
+
+/// Parses and validates the symbol mapping, collecting every bad entry.
+fn parse_mapping(
+    file: &str,
+    raw: HashMap<String, Value>,
+    errors: &mut Vec<MalformedEntry>,
+) -> TickerMapping {
+    let mut mapping = HashMap::new();
+    for (symbol, value) in raw {
+        let reason = match serde_json::from_value::<SymbolMapping>(value) {
+            Err(e) => e.to_string(),
+            Ok(m) if m.base.trim().is_empty() || m.other.trim().is_empty() => {
+                "empty base or other currency".to_string()
+            }
+            Ok(m) if m.base == m.other => format!("base and other are both {}", m.base),
+            Ok(m) => {
+                mapping.insert(symbol, m);
+                continue;
+            }
+        };
+        errors.push(MalformedEntry { file: file.to_string(), key: EntryKey::Name(symbol), reason });
+    }
+    mapping
+}
+
+/// Parses and validates price entries of type `T`, collecting every bad entry.
+fn parse_prices<T>(file: &str, raw: Vec<Value>, errors: &mut Vec<MalformedEntry>) -> Vec<Quote>
+where
+    T: DeserializeOwned + Into<Quote>,
+{
+    let mut ticks = Vec::new();
+    for (i, value) in raw.into_iter().enumerate() {
+        match serde_json::from_value::<T>(value).map(Into::<Quote>::into) {
+            Ok(tick) if tick.symbol.trim().is_empty() => errors.push(MalformedEntry {
+                file: file.to_string(),
+                key: EntryKey::Index(i),
+                reason: "empty symbol".to_string(),
+            }),
+            Ok(tick) => ticks.push(Quote { source: file.to_string(), ..tick }),
+            Err(e) => errors.push(MalformedEntry {
+                file: file.to_string(),
+                key: EntryKey::Index(i),
+                reason: e.to_string(),
+            }),
+        }
+    }
+    ticks
+}
+
+/// Reads price files in `format`, collecting every malformed entry.
+fn read_prices(
+    price_paths: &[PathBuf],
+    format: PriceFormat,
+    errors: &mut Vec<MalformedEntry>,
+) -> Result<Vec<Quote>, Box<dyn Error>> {
+    let mut json = Vec::new();
+    for price_path in price_paths {
+        let price_file = price_path.display().to_string();
+        let price_data = fs::read_to_string(price_path)
+            .map_err(|e| format!("Unable to read {}: {}", price_file, e))?;
+        let raw_prices: Vec<Value> = serde_json::from_str(&price_data)?;
+        json.extend(parse_entries(&price_file, raw_prices, format, errors));
+    }
+    Ok(json)
+}
+
+/// Parses raw price entries laid out as `format`, collecting every bad entry.
+pub(crate) fn parse_entries(
+    file: &str,
+    raw: Vec<Value>,
+    format: PriceFormat,
+    errors: &mut Vec<MalformedEntry>,
+) -> Vec<Quote> {
+    match format {
+        PriceFormat::Last => parse_prices::<PriceTick>(file, raw, errors),
+        PriceFormat::BookTicker => parse_prices::<BookTicker>(file, raw, errors),
+    }
+}
+
+/// Reads price data alone, for mappings that come from elsewhere.
+/// Fails with an `IngestError` listing every malformed entry in any file.
+pub fn prices(price_paths: &[PathBuf], format: PriceFormat) -> Result<Vec<Quote>, Box<dyn Error>> {
+    let mut errors = Vec::new();
+    let json = read_prices(price_paths, format, &mut errors)?;
+    if !errors.is_empty() {
+        return Err(Box::new(IngestError::new(errors)));
+    }
+    Ok(json)
+}
+
+/// Reads ticker mapping and price data from JSON files.
+/// Ticks from several price files, all in `format`, are concatenated in order.
+/// Fails with an `IngestError` listing every malformed entry in any file.
+pub fn data(
+    mapping_path: &Path,
+    price_paths: &[PathBuf],
+    format: PriceFormat,
+) -> Result<(TickerMapping, Vec<Quote>), Box<dyn Error>> {
+    let mut errors = Vec::new();
+
+    let mapping_file = mapping_path.display().to_string();
+    let data = fs::read_to_string(mapping_path)
+        .map_err(|e| format!("Unable to read {}: {}", mapping_file, e))?;
+    let raw_mapping: HashMap<String, Value> = serde_json::from_str(&data)?;
+    let ticker_mapping = parse_mapping(&mapping_file, raw_mapping, &mut errors);
+
+    let json = read_prices(price_paths, format, &mut errors)?;
+
+    if !errors.is_empty() {
+        return Err(Box::new(IngestError::new(errors)));
+    }
+    Ok((ticker_mapping, json))
+}
+
+/// A price tick that did not produce graph edges.
+#[derive(Debug, Serialize)]
+pub struct RejectedPrice {
+    pub symbol: String,
+    pub price: String,
+    pub reason: String,
+}
+
+/// What `graph_builder` did with its input, so skipped data is visible.
+#[derive(Debug, Default, Serialize)]
+pub struct IngestionReport {
+    /// Number of price ticks read.
+    pub ticks: usize,
+    /// Ticks that produced a pair of edges.
+    pub pairs_added: usize,
+    /// Symbols missing from the mapping.
+    pub unmapped_symbols: Vec<String>,
+    /// Prices that could not be used.
+    pub rejected_prices: Vec<RejectedPrice>,
+    /// Currencies left without any edge.
+    pub isolated_nodes: Vec<String>,
+    /// Symbols quoted more than once.
+    pub duplicate_symbols: Vec<String>,
+}
+
+impl IngestionReport {
+    /// Prints the counts as a table, followed by the offending entries.
+    pub fn print_table(&self) {
+        println!("Ingestion summary:");
+        println!("  {:<20} {:>8}", "category", "count");
+        println!("  {:<20} {:>8}", "price ticks", self.ticks);
+        println!("  {:<20} {:>8}", "pairs added", self.pairs_added);
+        println!("  {:<20} {:>8}", "unmapped symbols", self.unmapped_symbols.len());
+        println!("  {:<20} {:>8}", "rejected prices", self.rejected_prices.len());
+        println!("  {:<20} {:>8}", "isolated nodes", self.isolated_nodes.len());
+        println!("  {:<20} {:>8}", "duplicate symbols", self.duplicate_symbols.len());
+        for symbol in &self.unmapped_symbols {
+            println!("  unmapped: {}", symbol);
+        }
+        for rejected in &self.rejected_prices {
+            println!("  rejected: {} = {:?} ({})", rejected.symbol, rejected.price, rejected.reason);
+        }
+        for node in &self.isolated_nodes {
+            println!("  isolated: {}", node);
+        }
+        for symbol in &self.duplicate_symbols {
+            println!("  duplicate: {}", symbol);
+        }
+    }
+
+    /// Writes the report as pretty-printed JSON.
+    pub fn write_json(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn malformed_entries_sort_by_position_numerically() {
+        let raw: Vec<Value> = (0..12).map(|i| json!({ "symbol": format!("S{}", i) })).collect();
+        let mut errors = Vec::new();
+        assert!(parse_entries("b.json", raw, PriceFormat::Last, &mut errors).is_empty());
+        errors.reverse();
+        errors.push(MalformedEntry { file: "a.json".to_string(), key: EntryKey::Index(3), reason: String::new() });
+
+        let error = IngestError::new(errors);
+        let keys: Vec<String> = error.entries.iter().map(|e| format!("{}[{}]", e.file, e.key)).collect();
+        let expected: Vec<String> =
+            std::iter::once("a.json[3]".to_string()).chain((0..12).map(|i| format!("b.json[{}]", i))).collect();
+        assert_eq!(keys, expected);
+    }
+}
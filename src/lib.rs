@@ -0,0 +1,41 @@
+//! Arbitrage cycle detection on cryptocurrency price snapshots.
+//!
+//! Currencies become nodes and every quoted symbol becomes a pair of `Edge`s
+//! weighted `-log2(rate)`, so a loop of trades that multiplies money shows up
+//! as a negative cycle. The usual pipeline is:
+//!
+//! ```no_run
+//! use arbitrage::detect::{negative_finder, DetectionMode};
+//! use arbitrage::fees::FeeSchedule;
+//! use arbitrage::graph::{graph_builder, ParallelEdges};
+//! use arbitrage::ingest::{data, PriceFormat};
+//! use std::path::{Path, PathBuf};
+//!
+//! let prices = [PathBuf::from("mock_prices.js")];
+//! let (mapping, ticks) = data(Path::new("dict.json"), &prices, PriceFormat::Last)?;
+//! let (graph, _nodes, report) = graph_builder(mapping, ticks, &FeeSchedule::default(), ParallelEdges::KeepBest);
+//! report.print_table();
+//! if let Some(cycle) = negative_finder(&graph, DetectionMode::PerScc, 0.0) {
+//!     let path: Vec<&str> = cycle.nodes.iter().map(|&n| graph[n].as_str()).collect();
+//!     println!("{}", path.join(" -> "));
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+pub mod backtest;
+pub mod depth;
+pub mod detect;
+pub mod edge;
+pub mod exact;
+pub mod exchange;
+pub mod exchange_info;
+pub mod export;
+pub mod fees;
+pub mod graph;
+pub mod ingest;
+pub mod mapping;
+pub mod metrics;
+pub mod plan;
+pub mod removal;
+pub mod sample;
+pub mod update;
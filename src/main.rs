@@ -1,224 +1,643 @@
-extern crate petgraph;
-extern crate rand;
-extern crate serde_json;
-extern crate csv;
+//! Command-line front end over the `arbitrage` library.
 
-use petgraph::algo::find_negative_cycle;
+use arbitrage::backtest::{backtest, load_snapshots, BacktestConfig, snapshot_from_file};
+use arbitrage::depth::{load_depth, CycleSizer};
+use arbitrage::detect::enumerate::{best_cycle_through, enumerate_negative_cycles, rank_cycles, RankBy};
+use arbitrage::detect::mean::{howard_min_mean_cycle, karp_min_mean_cycle, MeanCycle};
+use arbitrage::detect::DetectionMode;
+use arbitrage::exact::{exceeds_threshold, retain_exact};
+use arbitrage::exchange::{multi_exchange_graph, ExchangeInput, TransferConfig};
+use arbitrage::export::{output_backtest_csv, output_cycles_csv, output_dot_file, output_windows_csv};
+use arbitrage::fees::FeeSchedule;
+use arbitrage::graph::{ArbGraph, GraphBuilder, ParallelEdges};
+use arbitrage::exchange_info::{load_exchange_info, FilterSimulator, RulesMap};
+use arbitrage::ingest::{self, data, IngestionReport, PriceFormat, Quote, TickerMapping};
+use arbitrage::mapping::{diff_mappings, mapping_from_exchange_info, mapping_from_suffixes, KNOWN_QUOTE_ASSETS};
+use arbitrage::metrics::{write_metrics, IterationMetrics};
+use arbitrage::plan::{render_plans, trade_plan, PlanFormat};
+use arbitrage::removal::{removal_loop, RemovalStrategy, StrategyKind};
+use arbitrage::sample::sample_subgraph;
+use clap::{Args, Parser, Subcommand};
 use petgraph::dot::Dot;
-use petgraph::graph::{DiGraph, NodeIndex};
-use petgraph::visit::EdgeRef;
-use rand::Rng;
-use serde_json::Value;
-use std::collections::HashMap;
+use petgraph::graph::NodeIndex;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
 use std::fs;
-use std::io::Write;
-use std::path::Path;
-
-/// Reads ticker mapping and price data from JSON files.
-fn data() -> (HashMap<String, Value>, Vec<Value>) {
-    let data = fs::read_to_string("./dict.json")
-        .expect("Unable to read dict.json");
-    let ticker_mapping: HashMap<String, Value> =
-        serde_json::from_str(&data).expect("JSON does not have correct format.");
-
-    let price_data = fs::read_to_string("./mock_prices.js")
-        .expect("Unable to read mock_prices.js");
-    let json: Vec<Value> =
-        serde_json::from_str(&price_data).expect("JSON does not have correct format.");
-    (ticker_mapping, json)
-}
-
-/// Builds a directed graph from the provided data.
-fn graph_builder(
-    ticker_mapping: HashMap<String, Value>,
-    json: Vec<Value>,
-) -> (DiGraph<String, f64>, HashMap<String, NodeIndex>) {
-    let mut graph = DiGraph::<String, f64>::new();
-    let mut nodes = HashMap::new();
-
-    for entry in json {
-        if let Some(symbol) = entry.get("symbol").and_then(|v| v.as_str()) {
-            if let Some(mapping) = ticker_mapping.get(symbol) {
-                if let Some(price_str) = entry.get("price").and_then(|v| v.as_str()) {
-                    if let Ok(price) = price_str.parse::<f64>() {
-                        let base = mapping.get("base").and_then(|v| v.as_str()).unwrap_or("");
-                        let other = mapping.get("other").and_then(|v| v.as_str()).unwrap_or("");
-
-                        let node_a = *nodes
-                            .entry(base.to_string())
-                            .or_insert_with(|| graph.add_node(base.to_string()));
-                        let node_b = *nodes
-                            .entry(other.to_string())
-                            .or_insert_with(|| graph.add_node(other.to_string()));
-
-                        if price > 0.0 {
-                            // Use the log2 transformation as weights.
-                            graph.add_edge(node_a, node_b, -price.log2());
-                            graph.add_edge(node_b, node_a, price.log2());
-                        }
-                    }
-                }
-            }
+use std::path::{Path, PathBuf};
+
+/// Graph, currency nodes by name and ingestion report, as built by `GraphBuilder::finish`.
+type LoadedGraph = (ArbGraph, HashMap<String, NodeIndex>, IngestionReport);
+
+/// Sizes sampled along each cycle's profit curve.
+const PROFIT_CURVE_POINTS: usize = 10;
+
+/// Prints a minimum mean cycle found by the named solver.
+fn report_mean_cycle(graph: &ArbGraph, solver: &str, cycle: Option<MeanCycle>) {
+    match cycle {
+        Some(cycle) => {
+            let path: Vec<&str> = cycle.nodes.iter().map(|&n| graph[n].as_str()).collect();
+            println!(
+                "  {}: mean log2 weight {:.6}, per-leg return {:.6} over {} legs: {}",
+                solver,
+                cycle.mean_weight,
+                cycle.per_leg_return(),
+                cycle.nodes.len(),
+                path.join(" -> ")
+            );
         }
+        None => println!("  {}: graph has no cycles.", solver),
     }
-    println!("Full graph built. DOT representation:\n{}", Dot::new(&graph));
-    (graph, nodes)
 }
 
-/// Creates an induced subgraph by sampling nodes.
-fn sample_subgraph(
-    graph: &DiGraph<String, f64>,
+/// Detects arbitrage cycles in crypto price snapshots.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Find, rank and remove negative cycles in one snapshot.
+    Detect {
+        #[command(flatten)]
+        input: InputArgs,
+        #[command(flatten)]
+        detect: DetectArgs,
+        /// Also write the ingestion report as JSON to this path.
+        #[arg(long)]
+        ingest_report: Option<PathBuf>,
+        /// L2 order book snapshots used to size each detected cycle.
+        #[arg(long)]
+        depth: Option<PathBuf>,
+        /// Amount of each cycle's first currency to push through the exchange's
+        /// order filters, reporting the post-rounding profit.
+        #[arg(long, requires = "exchange_info")]
+        notional: Option<f64>,
+        /// Report the best cycle starting and ending at this currency node;
+        /// repeat for several. Multi-exchange nodes are named `NAME:ASSET`.
+        #[arg(long = "anchor")]
+        anchors: Vec<String>,
+        #[command(flatten)]
+        plan: PlanArgs,
+    },
+    /// Repeat the removal loop over several sample ratios.
+    Sweep {
+        #[command(flatten)]
+        input: InputArgs,
+        #[command(flatten)]
+        detect: DetectArgs,
+        /// Sample ratios to try.
+        #[arg(long, value_delimiter = ',', default_value = "0.25,0.5,0.75,1.0")]
+        ratios: Vec<f64>,
+        /// Runs per sample ratio.
+        #[arg(long, default_value_t = 1)]
+        repeats: usize,
+    },
+    /// Build the graph and write it as DOT with the ingestion report, without detection.
+    Export {
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Enumerate cycles in successive snapshots and track how long each one lasts.
+    Backtest {
+        #[command(flatten)]
+        input: InputArgs,
+        #[command(flatten)]
+        cycles: CycleArgs,
+        /// Directory of price files or NDJSON stream of timestamped snapshots;
+        /// each `--prices` file is one snapshot when omitted.
+        #[arg(long)]
+        snapshots: Option<PathBuf>,
+    },
+    /// Work with the symbol mapping.
+    Mapping {
+        #[command(subcommand)]
+        command: MappingCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum MappingCommand {
+    /// Diff a provided mapping against one derived from exchangeInfo, or from
+    /// known quote asset suffixes of the symbols in the price files.
+    Check {
+        /// Mapping to check.
+        #[arg(long, default_value = "dict.json")]
+        mapping: PathBuf,
+        /// Derive from this exchangeInfo JSON instead of symbol suffixes.
+        #[arg(long)]
+        exchange_info: Option<PathBuf>,
+        /// Price files whose symbols are split by suffix, together with the mapping's own.
+        #[arg(long = "prices", default_value = "mock_prices.js")]
+        prices: Vec<PathBuf>,
+        #[arg(long, default_value = "last")]
+        price_format: PriceFormat,
+        /// Also write the derived mapping as JSON to this path.
+        #[arg(long)]
+        write: Option<PathBuf>,
+    },
+}
+
+/// Input files and output location shared by all subcommands.
+#[derive(Args, Debug)]
+struct InputArgs {
+    /// Symbol mapping with the base and other currency of each symbol.
+    #[arg(long, default_value = "dict.json")]
+    mapping: PathBuf,
+    /// Binance exchangeInfo JSON; replaces `--mapping` and supplies order filters.
+    #[arg(long)]
+    exchange_info: Option<PathBuf>,
+    /// Price snapshot file; repeat the flag to pass several.
+    #[arg(long = "prices", default_value = "mock_prices.js")]
+    prices: Vec<PathBuf>,
+    /// Layout of the price files: `last` trade prices or `book-ticker` best bid/ask.
+    #[arg(long, default_value = "last")]
+    price_format: PriceFormat,
+    /// Exchange-tagged price file, `NAME=PATH`; repeat for a multi-exchange graph
+    /// whose nodes are `NAME:ASSET`. Replaces `--prices` when given.
+    #[arg(long = "exchange")]
+    exchanges: Vec<ExchangeInput>,
+    /// Transfer cost JSON for moving assets between exchanges. Withdrawal fees
+    /// are fractions of the amount moved, approximating the flat per-withdrawal
+    /// fees exchanges charge; size them against a typical transfer.
+    #[arg(long)]
+    transfers: Option<PathBuf>,
+    /// Repeated currency pairs: `keep-best` or `keep-latest` rate, or `keep-all` edges.
+    #[arg(long, default_value = "keep-all")]
+    parallel_edges: ParallelEdges,
+    /// Fee schedule JSON applied to every fill; no fees when omitted.
+    #[arg(long)]
+    fees: Option<PathBuf>,
+    /// Directory receiving DOT files, CSVs and reports.
+    #[arg(long, default_value = ".")]
+    output_dir: PathBuf,
+    /// Print the full graph as DOT once it is built.
+    #[arg(long)]
+    print_dot: bool,
+}
+
+/// Which cycles count, shared by `detect`, `sweep` and `backtest`.
+#[derive(Args, Debug)]
+struct CycleArgs {
+    /// Longest cycle, in legs, to enumerate.
+    #[arg(long, default_value_t = 4)]
+    max_hops: usize,
+    /// Cycles are kept only if their exact return, recomputed from the raw
+    /// price strings, exceeds 1 plus this margin.
+    #[arg(long, default_value_t = 0.0, value_parser = parse_non_negative)]
+    epsilon: f64,
+    /// Minimum return per leg, in basis points, for a cycle to count; 0 accepts
+    /// any profit. A cycle of k legs must return (1 + bps / 10000)^k.
+    #[arg(long, default_value_t = 0.0, value_parser = parse_non_negative)]
+    min_return_bps: f64,
+}
+
+/// Sampling, detection and removal settings shared by `detect` and `sweep`.
+#[derive(Args, Debug)]
+struct DetectArgs {
+    /// Fraction of nodes kept when sampling the subgraph.
+    #[arg(long, default_value_t = 1.0)]
     sample_ratio: f64,
-) -> (DiGraph<String, f64>, HashMap<NodeIndex, NodeIndex>) {
-    let mut new_graph = DiGraph::<String, f64>::new();
-    let mut mapping = HashMap::new();
-    let mut rng = rand::thread_rng();
-
-    for node in graph.node_indices() {
-        if rng.gen::<f64>() < sample_ratio {
-            let label = graph[node].clone();
-            let new_node = new_graph.add_node(label);
-            mapping.insert(node, new_node);
-        }
+    /// Seed for node sampling; random when omitted.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// How each detected cycle is broken.
+    #[arg(long, default_value = "third-node")]
+    strategy: StrategyKind,
+    /// Where Bellman-Ford starts from.
+    #[arg(long, default_value = "per-scc")]
+    detection_mode: DetectionMode,
+    /// Ordering for the ranked cycle report.
+    #[arg(long, default_value = "return")]
+    rank_by: RankBy,
+    /// Number of ranked cycles printed.
+    #[arg(long, default_value_t = 20)]
+    top_k: usize,
+    #[command(flatten)]
+    cycles: CycleArgs,
+}
+
+/// A finite, non-negative threshold.
+fn parse_non_negative(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(value) if value.is_finite() && value >= 0.0 => Ok(value),
+        _ => Err(format!("'{}' is not a finite, non-negative number", s)),
     }
-    // Add edges if both endpoints are present.
-    for edge in graph.edge_indices() {
-        let (src, dst) = graph.edge_endpoints(edge).unwrap();
-        if let (Some(&new_src), Some(&new_dst)) = (mapping.get(&src), mapping.get(&dst)) {
-            new_graph.add_edge(new_src, new_dst, graph[edge]);
+}
+
+/// Trade plans rendered for the ranked cycles of `detect`.
+#[derive(Args, Debug)]
+struct PlanArgs {
+    /// Asset every plan starts and ends in; cycles not passing through it get no
+    /// plan. Each cycle starts at its first currency when omitted.
+    #[arg(long)]
+    plan_base: Option<String>,
+    /// Amount of the base asset the plans start with.
+    #[arg(long, default_value_t = 1000.0)]
+    plan_amount: f64,
+    /// Plan layout: `text`, `json` or `markdown`.
+    #[arg(long, default_value = "text")]
+    plan_format: PlanFormat,
+}
+
+impl DetectArgs {
+    /// Random number generator for sampling, seeded when requested.
+    fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
         }
     }
-    (new_graph, mapping)
+
+    fn strategy(&self) -> Box<dyn RemovalStrategy> {
+        self.strategy.strategy()
+    }
 }
 
-/// Writes the current graph in DOT format.
-fn output_dot_file(graph: &DiGraph<String, f64>, dot_filename: &str) -> Result<(), Box<dyn Error>> {
-    let dot_dir = "dot_files";
-    fs::create_dir_all(dot_dir)?;
-    let dot_path = Path::new(dot_dir).join(dot_filename);
-    let dot_str = format!("{:?}", Dot::new(graph));
-    let mut file = fs::File::create(dot_path)?;
-    file.write_all(dot_str.as_bytes())?;
-    Ok(())
+/// The fee schedule from `--fees`, or no fees.
+fn load_fees(input: &InputArgs) -> Result<FeeSchedule, Box<dyn Error>> {
+    match &input.fees {
+        Some(path) => FeeSchedule::load(path),
+        None => Ok(FeeSchedule::default()),
+    }
 }
 
-/// Detects a negative cycle in the graph.
-/// Returns the cycle as a vector of node indices if found.
-fn negative_finder(graph: &DiGraph<String, f64>) -> Option<Vec<NodeIndex>> {
-    let path = find_negative_cycle(graph, NodeIndex::new(0));
-    println!("Checking for negative cycles...");
-    if let Some(p) = path {
-        println!("Negative cycle found.");
-        Some(p)
-    } else {
-        println!("No negative cycle detected.");
-        None
+/// Reads the symbol mapping, from exchangeInfo when given, and the price files.
+fn read_inputs(input: &InputArgs, prices: &[PathBuf]) -> Result<(TickerMapping, RulesMap, Vec<Quote>), Box<dyn Error>> {
+    match &input.exchange_info {
+        Some(path) => {
+            let (mapping, rules) = load_exchange_info(path)?;
+            Ok((mapping, rules, ingest::prices(prices, input.price_format)?))
+        }
+        None => {
+            let (mapping, json) = data(&input.mapping, prices, input.price_format)?;
+            Ok((mapping, RulesMap::new(), json))
+        }
     }
 }
 
-/// Removes one node from the detected negative cycle.
-fn remove_nodes(graph: &mut DiGraph<String, f64>, nodes_to_remove: &[NodeIndex]) {
-    if nodes_to_remove.len() > 2 {
-        let node_to_remove = nodes_to_remove[2];
-        graph.remove_node(node_to_remove);
-        println!("Removed node {} from the negative cycle.", node_to_remove.index());
+/// Replays every detected cycle with `notional` of its first currency under the
+/// exchange's order filters, recording the post-rounding profit.
+fn apply_filters(input: &InputArgs, notional: f64, metrics: &mut [IterationMetrics]) -> Result<(), Box<dyn Error>> {
+    let Some(path) = &input.exchange_info else {
+        return Ok(());
+    };
+    println!("Simulating order filters from {} with notional {}...", path.display(), notional);
+    let simulator = FilterSimulator::new(load_exchange_info(path)?.1);
+    for (i, m) in metrics.iter_mut().enumerate() {
+        match simulator.rounded_profit(&m.legs, notional) {
+            Ok(profit) => {
+                println!("  Cycle {}: profit {} after rounding (raw {})", i, profit, m.profit);
+                m.rounded_profit = Some(profit);
+            }
+            Err(reason) => println!("  Cycle {}: rejected, {}", i, reason),
+        }
     }
+    Ok(())
 }
 
-/// Computes the average out-degree of nodes in the graph.
-fn average_out_degree(graph: &DiGraph<String, f64>) -> f64 {
-    if graph.node_count() == 0 {
-        return 0.0;
+/// Sizes every detected cycle against order book depth, writing each one's
+/// return versus size to `profit_curve.csv`.
+fn size_cycles(input: &InputArgs, depth: &Path, metrics: &mut [IterationMetrics]) -> Result<(), Box<dyn Error>> {
+    println!("Sizing cycles against order book depth from {}...", depth.display());
+    let sizer = CycleSizer::new(load_depth(depth)?);
+
+    let curve_path = input.output_dir.join("profit_curve.csv");
+    let mut wtr = csv::Writer::from_path(&curve_path)?;
+    wtr.write_record(["iteration", "size", "return"])?;
+    for (i, m) in metrics.iter_mut().enumerate() {
+        m.max_size = sizer.max_size(&m.legs);
+        match m.max_size {
+            Some(size) => println!("  Cycle {}: max size {} {}", i, size, m.currencies[0]),
+            None => println!("  Cycle {}: no depth for every leg of {}", i, m.currencies.join(" -> ")),
+        }
+        for (size, ret) in sizer.profit_curve(&m.legs, PROFIT_CURVE_POINTS) {
+            wtr.write_record(&[i.to_string(), size.to_string(), ret.to_string()])?;
+        }
     }
-    let total: usize = graph.node_indices()
-        .map(|n| graph.neighbors_directed(n, petgraph::Direction::Outgoing).count())
-        .sum();
-    total as f64 / graph.node_count() as f64
+    wtr.flush()?;
+    println!("Profit curves saved to {}.", curve_path.display());
+    Ok(())
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    // Step 1: Set sample ratio.
-    let sample_ratio = 1.0;
-    println!("Setting sample ratio to {} ({}% of nodes).", sample_ratio, sample_ratio * 100.0);
+/// Reads the input files and builds the full graph.
+/// Exchange-tagged inputs are combined into one multi-exchange graph.
+/// With `--print-dot` the graph is also printed as DOT.
+fn load_graph(input: &InputArgs, prices: &[PathBuf]) -> Result<LoadedGraph, Box<dyn Error>> {
+    let loaded = build_graph(input, prices)?;
+    if input.print_dot {
+        println!("Full graph built. DOT representation:\n{}", Dot::new(&loaded.0));
+    }
+    Ok(loaded)
+}
 
-    // Step 2: Read data.
+fn build_graph(input: &InputArgs, prices: &[PathBuf]) -> Result<LoadedGraph, Box<dyn Error>> {
+    let fees = load_fees(input)?;
     println!("Reading data from files...");
-    let (ticker_mapping, json) = data();
+    if input.exchanges.is_empty() {
+        let (ticker_mapping, rules, json) = read_inputs(input, prices)?;
+        println!("Building the full graph (default {:?} fee {})...", fees.tier, fees.rate(""));
+        let mut builder = GraphBuilder::with_rules(input.parallel_edges, rules);
+        builder.add_quotes(&ticker_mapping, json, &fees, None);
+        return Ok(builder.finish());
+    }
+
+    let transfers = match &input.transfers {
+        Some(path) => TransferConfig::load(path)?,
+        None => TransferConfig::default(),
+    };
+    let mut names = HashSet::new();
+    if let Some(duplicate) = input.exchanges.iter().find(|e| !names.insert(&e.name)) {
+        return Err(format!("exchange '{}' given more than once", duplicate.name).into());
+    }
+    // One mapping and one set of rules serve every exchange.
+    let (ticker_mapping, rules, _) = read_inputs(input, &[])?;
+    let mut exchanges = Vec::new();
+    for exchange in &input.exchanges {
+        let json = ingest::prices(std::slice::from_ref(&exchange.path), input.price_format)?;
+        exchanges.push((exchange.name.clone(), json));
+    }
+    println!("Building the multi-exchange graph over {} exchanges...", exchanges.len());
+    Ok(multi_exchange_graph(&ticker_mapping, rules, exchanges, &fees, &transfers, input.parallel_edges))
+}
 
-    // Step 3: Build full graph.
-    println!("Building the full graph...");
-    let (full_graph, _) = graph_builder(ticker_mapping, json);
+/// `detect`: the full single-snapshot pipeline.
+fn run_detect(
+    input: &InputArgs,
+    detect: &DetectArgs,
+    ingest_report: Option<&Path>,
+    depth: Option<&Path>,
+    notional: Option<f64>,
+    anchors: &[String],
+    plan: &PlanArgs,
+) -> Result<(), Box<dyn Error>> {
+    let strategy = detect.strategy();
+    println!("Using detection mode {:?}.", detect.detection_mode);
+    println!("Using removal strategy {}.", strategy.name());
+    println!("Setting sample ratio to {} ({}% of nodes).", detect.sample_ratio, detect.sample_ratio * 100.0);
+    fs::create_dir_all(&input.output_dir)?;
 
-    // Step 4: Sample subgraph.
-    println!("Sampling subgraph using sample ratio {}...", sample_ratio);
-    let (mut graph, _mapping) = sample_subgraph(&full_graph, sample_ratio);
+    let (full_graph, nodes, ingestion_report) = load_graph(input, &input.prices)?;
+    ingestion_report.print_table();
+    if let Some(path) = ingest_report {
+        ingestion_report.write_json(path)?;
+        println!("Ingestion report saved to {}.", path.display());
+    }
+
+    println!("Sampling subgraph using sample ratio {}...", detect.sample_ratio);
+    let mut graph = sample_subgraph(&full_graph, detect.sample_ratio, &mut detect.rng());
 
-    // Step 5: Output initial DOT file.
     println!("Generating DOT file for the initial sampled subgraph...");
-    output_dot_file(&graph, "graph_updated_0.dot")?;
-
-    // Prepare vectors to store metrics.
-    let mut profit_history: Vec<f64> = Vec::new();
-    let mut cycle_length_history: Vec<usize> = Vec::new();
-    let mut centrality_history: Vec<f64> = Vec::new();
-    let mut iterations = 0;
-
-    // Step 6: Detect and remove negative cycles, recording metrics.
-    while let Some(negative_cycle) = negative_finder(&graph) {
-        // Compute cycle profit by traversing the cycle.
-        let mut cycle_profit = 1.0;
-        for window in negative_cycle.windows(2) {
-            if let [start_node, end_node] = window {
-                if let Some(edge) = graph.find_edge(*start_node, *end_node) {
-                    let weight = graph[edge];
-                    let price = 2f64.powf(-weight);
-                    cycle_profit *= price;
-                }
-            }
+    let dot_dir = input.output_dir.join("dot_files");
+    output_dot_file(&graph, &dot_dir, "graph_updated_0.dot")?;
+
+    // Enumerate all short arbitrage loops before the removal loop mutates the graph.
+    println!("Enumerating simple negative cycles with at most {} hops...", detect.cycles.max_hops);
+    let mut cycles = enumerate_negative_cycles(&graph, detect.cycles.max_hops);
+    cycles.retain(|c| c.clears_threshold(&graph, detect.cycles.min_return_bps));
+    let discarded = retain_exact(&graph, &mut cycles, detect.cycles.epsilon, |c| &c.edges);
+    println!("Exact check discarded {} cycles not returning more than 1 + {}.", discarded, detect.cycles.epsilon);
+    for legs in 1..=detect.cycles.max_hops {
+        let count = cycles.iter().filter(|c| c.nodes.len() == legs).count();
+        if count > 0 {
+            println!("  {}-leg cycles: {}", legs, count);
         }
-        // Close the cycle: from the last node back to the first.
-        if let (Some(&last_node), Some(&first_node)) = (negative_cycle.last(), negative_cycle.first()) {
-            if let Some(edge) = graph.find_edge(last_node, first_node) {
-                let weight = graph[edge];
-                let price = 2f64.powf(-weight);
-                cycle_profit *= price;
+    }
+    rank_cycles(&mut cycles, detect.rank_by, usize::MAX);
+    let cycles_path = input.output_dir.join("cycles.csv");
+    output_cycles_csv(&cycles, &cycles_path)?;
+    println!("{} cycles saved to {}, ranked by {:?}.", cycles.len(), cycles_path.display(), detect.rank_by);
+
+    // Report the most profitable opportunities.
+    println!("Top {} cycles by {:?}:", detect.top_k, detect.rank_by);
+    for (rank, cycle) in cycles.iter().take(detect.top_k).enumerate() {
+        println!(
+            "  #{} profit {:.6} (per leg {:.6}): {}",
+            rank + 1,
+            cycle.rate_product,
+            cycle.geometric_mean_return(),
+            cycle.currencies.join(" -> ")
+        );
+        let legs: Vec<String> = cycle.edges.iter().map(|&e| graph[e].to_string()).collect();
+        println!("      {}", legs.join(", "));
+    }
+
+    for anchor in anchors {
+        let Some(&node) = nodes.get(anchor) else {
+            return Err(format!("unknown anchor currency '{}'", anchor).into());
+        };
+        let best = best_cycle_through(&graph, node, detect.cycles.max_hops, detect.rank_by)
+            .filter(|c| c.clears_threshold(&graph, detect.cycles.min_return_bps))
+            .filter(|c| exceeds_threshold(&graph, &c.edges, detect.cycles.epsilon).unwrap_or(false));
+        match best {
+            Some(cycle) => {
+                let mut path = cycle.currencies.clone();
+                path.push(anchor.clone());
+                println!("Best cycle from {}: profit {:.6}: {}", anchor, cycle.rate_product, path.join(" -> "));
+                let legs: Vec<String> = cycle.edges.iter().map(|&e| graph[e].to_string()).collect();
+                println!("      {}", legs.join(", "));
             }
+            None => println!("No negative cycle of at most {} hops through {}.", detect.cycles.max_hops, anchor),
         }
-        println!("Cycle profit: {}", cycle_profit);
+    }
 
-        // Record metrics.
-        profit_history.push(cycle_profit);
-        cycle_length_history.push(negative_cycle.len());
-        let avg_deg = average_out_degree(&graph);
-        centrality_history.push(avg_deg);
-        println!("Average out-degree: {}", avg_deg);
+    let plans: Vec<_> = cycles
+        .iter()
+        .take(detect.top_k)
+        .filter_map(|cycle| trade_plan(&graph, &cycle.edges, plan.plan_base.as_deref(), plan.plan_amount))
+        .collect();
+    let rendered = render_plans(&plans, plan.plan_format)?;
+    println!("Trade plans for {} of the top {} cycles:", plans.len(), detect.top_k);
+    println!("{}", rendered);
+    let plans_path = input.output_dir.join(format!("trade_plans.{}", plan.plan_format.extension()));
+    fs::write(&plans_path, rendered)?;
+    println!("Trade plans saved to {}.", plans_path.display());
 
-        // Remove a node from the cycle.
-        remove_nodes(&mut graph, &negative_cycle);
-        iterations += 1;
+    // Length-normalised view of the worst inconsistency in the market.
+    println!("Computing minimum mean cycle...");
+    report_mean_cycle(&graph, "Karp", karp_min_mean_cycle(&graph));
+    report_mean_cycle(&graph, "Howard", howard_min_mean_cycle(&graph));
 
-        // Save updated DOT file.
-        let dot_filename = format!("graph_updated_{}.dot", iterations);
-        output_dot_file(&graph, &dot_filename)?;
+    // Detect and remove negative cycles, recording metrics.
+    let mut metrics = removal_loop(
+        &mut graph,
+        detect.detection_mode,
+        strategy.as_ref(),
+        Some(&dot_dir),
+        detect.cycles.epsilon,
+        detect.cycles.min_return_bps,
+    )?
+    .metrics;
+    if let Some(depth) = depth {
+        size_cycles(input, depth, &mut metrics)?;
+    }
+    if let Some(notional) = notional {
+        apply_filters(input, notional, &mut metrics)?;
     }
 
-    println!("Processing complete. No more negative cycles detected.");
+    let metrics_path = input.output_dir.join("metrics.csv");
+    write_metrics(&metrics_path, &metrics, strategy.name())?;
+    println!("Metrics saved to {}.", metrics_path.display());
+    Ok(())
+}
+
+/// `sweep`: removal loop statistics across sample ratios.
+fn run_sweep(input: &InputArgs, detect: &DetectArgs, ratios: &[f64], repeats: usize) -> Result<(), Box<dyn Error>> {
+    let strategy = detect.strategy();
+    fs::create_dir_all(&input.output_dir)?;
+    let (full_graph, _, _) = load_graph(input, &input.prices)?;
+    let mut rng = detect.rng();
 
-    // Step 7: Write metrics to a CSV file.
-    let mut wtr = csv::Writer::from_path("metrics.csv")?;
-    wtr.write_record(&["iteration", "profit", "cycle_length", "centrality"])?;
-    for (i, ((&profit, &cycle_length), &centrality)) in
-        profit_history.iter().zip(&cycle_length_history).zip(&centrality_history).enumerate() {
+    let sweep_path = input.output_dir.join("sweep.csv");
+    let mut wtr = csv::Writer::from_path(&sweep_path)?;
+    wtr.write_record([
+        "sample_ratio",
+        "run",
+        "nodes",
+        "edges",
+        "cycles",
+        "detections",
+        "discarded",
+        "best_profit",
+        "strategy",
+    ])?;
+    for &ratio in ratios {
+        for run in 0..repeats {
+            println!("Sampling subgraph using sample ratio {} (run {})...", ratio, run);
+            let mut graph = sample_subgraph(&full_graph, ratio, &mut rng);
+            let (nodes, edges) = (graph.node_count(), graph.edge_count());
+            let removal = removal_loop(
+                &mut graph,
+                detect.detection_mode,
+                strategy.as_ref(),
+                None,
+                detect.cycles.epsilon,
+                detect.cycles.min_return_bps,
+            )?;
+            let best_profit = removal.metrics.iter().map(|m| m.profit).fold(1.0, f64::max);
             wtr.write_record(&[
-                i.to_string(),
-                profit.to_string(),
-                cycle_length.to_string(),
-                centrality.to_string()
+                ratio.to_string(),
+                run.to_string(),
+                nodes.to_string(),
+                edges.to_string(),
+                removal.metrics.len().to_string(),
+                removal.detections.to_string(),
+                removal.discarded.to_string(),
+                best_profit.to_string(),
+                strategy.name().to_string(),
             ])?;
+        }
     }
     wtr.flush()?;
-    println!("Metrics saved to metrics.csv.");
+    println!("Sweep results saved to {}.", sweep_path.display());
+    Ok(())
+}
+
+/// `export`: the full graph as DOT plus the ingestion report.
+fn run_export(input: &InputArgs) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(&input.output_dir)?;
+    let (graph, _, ingestion_report) = load_graph(input, &input.prices)?;
+    ingestion_report.print_table();
+    output_dot_file(&graph, &input.output_dir, "graph.dot")?;
+    let report_path = input.output_dir.join("ingestion.json");
+    ingestion_report.write_json(&report_path)?;
+    println!(
+        "Graph saved to {} and ingestion report to {}.",
+        input.output_dir.join("graph.dot").display(),
+        report_path.display()
+    );
+    Ok(())
+}
+
+/// `backtest`: cycle enumeration per snapshot, with how long each opportunity lasts.
+fn run_backtest(input: &InputArgs, cycles: &CycleArgs, snapshots: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    if !input.exchanges.is_empty() {
+        return Err("backtest replays single-exchange snapshots; use --prices or --snapshots instead of --exchange".into());
+    }
+    fs::create_dir_all(&input.output_dir)?;
+    let snapshots = match snapshots {
+        Some(path) => load_snapshots(path, input.price_format)?,
+        None => input
+            .prices
+            .iter()
+            .map(|path| snapshot_from_file(path, input.price_format))
+            .collect::<Result<_, _>>()?,
+    };
+    let (ticker_mapping, rules, _) = read_inputs(input, &[])?;
+    println!("Backtesting {} snapshots with at most {} hops...", snapshots.len(), cycles.max_hops);
+    let fees = load_fees(input)?;
+    let config = BacktestConfig {
+        policy: input.parallel_edges,
+        rules,
+        max_len: cycles.max_hops,
+        epsilon: cycles.epsilon,
+        min_return_bps: cycles.min_return_bps,
+    };
+    let (ticks, windows) = backtest(&ticker_mapping, snapshots, &fees, config);
 
+    let backtest_path = input.output_dir.join("backtest.csv");
+    output_backtest_csv(&ticks, &backtest_path)?;
+    let windows_path = input.output_dir.join("windows.csv");
+    output_windows_csv(&windows, &windows_path)?;
+    println!(
+        "Backtest results saved to {} and {} opportunity windows to {}.",
+        backtest_path.display(),
+        windows.len(),
+        windows_path.display()
+    );
     Ok(())
 }
+
+/// `mapping check`: fails when the provided mapping differs from the derived one.
+fn run_mapping_check(
+    mapping: &Path,
+    exchange_info: Option<&Path>,
+    prices: &[PathBuf],
+    price_format: PriceFormat,
+    write: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    let (provided, _) = data(mapping, &[], price_format)?;
+    let derived = match exchange_info {
+        Some(path) => {
+            println!("Deriving the mapping from {}...", path.display());
+            mapping_from_exchange_info(path)?
+        }
+        None => {
+            println!("Deriving the mapping from quote asset suffixes...");
+            let quotes = ingest::prices(prices, price_format)?;
+            let symbols = quotes.iter().map(|q| q.symbol.as_str()).chain(provided.keys().map(String::as_str));
+            mapping_from_suffixes(symbols, KNOWN_QUOTE_ASSETS)
+        }
+    };
+    if let Some(path) = write {
+        let sorted: BTreeMap<_, _> = derived.iter().collect();
+        fs::write(path, serde_json::to_string_pretty(&sorted)?)?;
+        println!("Derived mapping of {} symbols saved to {}.", derived.len(), path.display());
+    }
+
+    let diff = diff_mappings(&provided, &derived);
+    diff.print();
+    if diff.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} differs from the derived mapping", mapping.display()).into())
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    match Cli::parse().command {
+        Command::Detect { input, detect, ingest_report, depth, notional, anchors, plan } => {
+            run_detect(&input, &detect, ingest_report.as_deref(), depth.as_deref(), notional, &anchors, &plan)
+        }
+        Command::Sweep { input, detect, ratios, repeats } => run_sweep(&input, &detect, &ratios, repeats),
+        Command::Export { input } => run_export(&input),
+        Command::Backtest { input, cycles, snapshots } => run_backtest(&input, &cycles, snapshots.as_deref()),
+        Command::Mapping {
+            command: MappingCommand::Check { mapping, exchange_info, prices, price_format, write },
+        } => run_mapping_check(&mapping, exchange_info.as_deref(), &prices, price_format, write.as_deref()),
+    }
+}
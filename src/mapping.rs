@@ -0,0 +1,145 @@
+//! Deriving the symbol mapping instead of maintaining dict.json by hand.
+
+use crate::exchange_info::load_exchange_info;
+use crate::ingest::{SymbolMapping, TickerMapping};
+use std::collections::{BTreeSet, HashSet};
+use std::error::Error;
+use std::path::Path;
+
+/// Quote assets of the symbols in dict.json, used when no exchangeInfo is at hand.
+pub const KNOWN_QUOTE_ASSETS: &[&str] = &[
+    "USDT", "BTC", "BUSD", "BNB", "ETH", "TRY", "FDUSD", "TUSD", "USDC", "EUR", "BRL", "PAX", "BIDR", "AUD", "RUB",
+    "GBP", "BKRW", "NGN", "UAH", "ZAR", "DAI", "IDRT", "UST", "PLN", "RON", "USDS", "VAI", "USDP", "AEUR", "JPY",
+    "XRP", "TRX", "BVND", "ARS", "DOGE", "DOT",
+];
+
+/// Mapping of every trading symbol in an exchangeInfo document.
+pub fn mapping_from_exchange_info(path: &Path) -> Result<TickerMapping, Box<dyn Error>> {
+    Ok(load_exchange_info(path)?.0)
+}
+
+/// Splits each symbol at a quote asset it ends with. When several fit, as with
+/// `EUR` and `AEUR` in `ADAEUR`, the base trading against the most other quote
+/// assets wins, then the longest quote asset.
+/// Symbols that end with none of them, or consist only of one, are left out.
+pub fn mapping_from_suffixes<'a>(symbols: impl IntoIterator<Item = &'a str>, quote_assets: &[&str]) -> TickerMapping {
+    let symbols: HashSet<&str> = symbols.into_iter().collect();
+    let listings = |base: &str, quote: &str| {
+        quote_assets
+            .iter()
+            .filter(|&&other| other != quote && symbols.contains(format!("{}{}", base, other).as_str()))
+            .count()
+    };
+
+    let mut mapping = TickerMapping::new();
+    for &symbol in &symbols {
+        let split = quote_assets
+            .iter()
+            .filter(|quote| symbol.len() > quote.len() && symbol.ends_with(*quote))
+            .map(|&quote| (&symbol[..symbol.len() - quote.len()], quote))
+            .max_by_key(|&(base, quote)| (listings(base, quote), quote.len()));
+        if let Some((base, quote)) = split {
+            mapping.insert(symbol.to_string(), SymbolMapping { base: base.to_string(), other: quote.to_string() });
+        }
+    }
+    mapping
+}
+
+/// Differences between a provided mapping and a derived one, each list sorted by symbol.
+#[derive(Debug, Default)]
+pub struct MappingDiff {
+    /// Symbols only the derived mapping knows.
+    pub missing: Vec<String>,
+    /// Symbols only the provided mapping knows.
+    pub extra: Vec<String>,
+    /// Symbols whose currencies disagree, as `(symbol, provided, derived)`.
+    pub mismatched: Vec<(String, SymbolMapping, SymbolMapping)>,
+}
+
+impl MappingDiff {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.mismatched.is_empty()
+    }
+
+    /// Prints every difference, one per line.
+    pub fn print(&self) {
+        println!(
+            "{} missing, {} extra, {} mismatched symbols.",
+            self.missing.len(),
+            self.extra.len(),
+            self.mismatched.len()
+        );
+        for symbol in &self.missing {
+            println!("  missing     {}", symbol);
+        }
+        for symbol in &self.extra {
+            println!("  extra       {}", symbol);
+        }
+        for (symbol, provided, derived) in &self.mismatched {
+            println!(
+                "  mismatched  {}: {}/{} provided, {}/{} derived",
+                symbol, provided.base, provided.other, derived.base, derived.other
+            );
+        }
+    }
+}
+
+/// Compares `provided` against `derived`.
+pub fn diff_mappings(provided: &TickerMapping, derived: &TickerMapping) -> MappingDiff {
+    let symbols: BTreeSet<&String> = provided.keys().chain(derived.keys()).collect();
+    let mut diff = MappingDiff::default();
+    for symbol in symbols {
+        match (provided.get(symbol), derived.get(symbol)) {
+            (None, Some(_)) => diff.missing.push(symbol.clone()),
+            (Some(_), None) => diff.extra.push(symbol.clone()),
+            (Some(p), Some(d)) if p.base != d.base || p.other != d.other => {
+                diff.mismatched.push((symbol.clone(), p.clone(), d.clone()))
+            }
+            _ => {}
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::fixtures::mapping;
+
+    fn split(mapping: &TickerMapping, symbol: &str) -> Option<(String, String)> {
+        mapping.get(symbol).map(|m| (m.base.clone(), m.other.clone()))
+    }
+
+    #[test]
+    fn suffix_ties_go_to_the_most_listed_base_then_the_longest_quote() {
+        let quotes = ["USDT", "EUR", "AEUR"];
+        // ADA also trades against USDT, AD against nothing: the shorter quote wins.
+        let derived = mapping_from_suffixes(["ADAEUR", "ADAUSDT"], &quotes);
+        assert_eq!(split(&derived, "ADAEUR"), Some(("ADA".to_string(), "EUR".to_string())));
+
+        // Neither XA nor X trades elsewhere: the longer quote wins.
+        let derived = mapping_from_suffixes(["XAEUR"], &quotes);
+        assert_eq!(split(&derived, "XAEUR"), Some(("X".to_string(), "AEUR".to_string())));
+    }
+
+    #[test]
+    fn symbols_without_a_base_are_left_out() {
+        let derived = mapping_from_suffixes(["USDT", "EUR", "FOOBAR", "BTCUSDT"], &["USDT", "EUR", "AEUR"]);
+        assert_eq!(derived.len(), 1);
+        assert_eq!(split(&derived, "BTCUSDT"), Some(("BTC".to_string(), "USDT".to_string())));
+    }
+
+    #[test]
+    fn diff_lists_missing_extra_and_mismatched_symbols() {
+        let provided = mapping(&[("ETHBTC", "ETH", "BTC"), ("ADAEUR", "AD", "AEUR"), ("OLDBTC", "OLD", "BTC")]);
+        let derived = mapping(&[("ETHBTC", "ETH", "BTC"), ("ADAEUR", "ADA", "EUR"), ("NEWBTC", "NEW", "BTC")]);
+        assert!(diff_mappings(&provided, &provided).is_empty());
+        let diff = diff_mappings(&provided, &derived);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.missing, ["NEWBTC"]);
+        assert_eq!(diff.extra, ["OLDBTC"]);
+        assert_eq!(diff.mismatched.len(), 1);
+        let (symbol, provided, derived) = &diff.mismatched[0];
+        assert_eq!((symbol.as_str(), provided.base.as_str(), derived.base.as_str()), ("ADAEUR", "AD", "ADA"));
+    }
+}
@@ -0,0 +1,50 @@
+//! Per-iteration statistics of the detect-and-remove loop.
+
+use crate::edge::Edge;
+use std::error::Error;
+use std::path::Path;
+
+/// One distinct cycle found by the detect-and-remove loop.
+pub struct IterationMetrics {
+    /// Product of rates around the detected cycle.
+    pub profit: f64,
+    /// Largest size, in the cycle's first currency, that still breaks even
+    /// against order book depth; `None` without depth data.
+    pub max_size: Option<f64>,
+    /// Return at the simulated notional once orders respect the exchange's
+    /// lot size, tick size and minimum notional; `None` when not simulated or rejected.
+    pub rounded_profit: Option<f64>,
+    /// Currencies around the cycle, in trading order.
+    pub currencies: Vec<String>,
+    /// Edges the cycle was detected on, `legs[i]` leaving `currencies[i]`.
+    pub legs: Vec<Edge>,
+    /// Times the loop detected this cycle, in any rotation.
+    pub detections: usize,
+    /// Number of legs in the cycle.
+    pub cycle_length: usize,
+    /// Average out-degree of the graph before the cycle was broken.
+    pub centrality: f64,
+    /// Strongly connected component the cycle came from.
+    pub scc: usize,
+}
+
+/// Writes per-cycle metrics as CSV.
+pub fn write_metrics(path: &Path, metrics: &[IterationMetrics], strategy: &str) -> Result<(), Box<dyn Error>> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["iteration", "profit", "rounded_profit", "max_size", "detections", "cycle_length", "centrality", "scc", "strategy"])?;
+    for (i, m) in metrics.iter().enumerate() {
+        wtr.write_record(&[
+            i.to_string(),
+            m.profit.to_string(),
+            m.rounded_profit.map(|p| p.to_string()).unwrap_or_default(),
+            m.max_size.map(|s| s.to_string()).unwrap_or_default(),
+            m.detections.to_string(),
+            m.cycle_length.to_string(),
+            m.centrality.to_string(),
+            m.scc.to_string(),
+            strategy.to_string(),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
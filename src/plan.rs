@@ -0,0 +1,234 @@
+//! Turning a detected cycle into an ordered list of trades.
+
+use crate::edge::Side;
+use crate::graph::ArbGraph;
+use clap::ValueEnum;
+use petgraph::graph::EdgeIndex;
+use serde::Serialize;
+use std::fmt::Write;
+
+/// One trade of a plan and the balance it leaves.
+#[derive(Debug, Serialize)]
+pub struct PlanLeg {
+    /// Position in the plan, from 1.
+    pub leg: usize,
+    pub from: String,
+    pub to: String,
+    pub symbol: String,
+    pub side: Side,
+    /// Price as quoted in the input.
+    pub price: String,
+    /// Units of `to` received per unit of `from`, net of fees.
+    pub rate: f64,
+    pub balance_before: f64,
+    pub balance_after: f64,
+    /// `LOT_SIZE` order quantity bounds, in the symbol's base currency.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_qty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_qty: Option<f64>,
+    /// Base quantity quoted at `price`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_qty: Option<f64>,
+    /// When the price was quoted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+}
+
+impl PlanLeg {
+    /// Quantity limits and quote time, e.g. `lot 0.001-100, top 3.1, at 1714564800000`;
+    /// empty when none are known.
+    pub fn limits(&self) -> String {
+        let mut parts = Vec::new();
+        match (self.min_qty, self.max_qty) {
+            (None, None) => {}
+            (min, max) => parts.push(format!(
+                "lot {}-{}",
+                min.map_or("0".to_string(), |q| q.to_string()),
+                max.map_or("any".to_string(), |q| q.to_string())
+            )),
+        }
+        if let Some(qty) = self.top_qty {
+            parts.push(format!("top {}", qty));
+        }
+        if let Some(timestamp) = &self.timestamp {
+            parts.push(format!("at {}", timestamp));
+        }
+        parts.join(", ")
+    }
+}
+
+/// A cycle as trades, starting and ending in `base`.
+#[derive(Debug, Serialize)]
+pub struct TradePlan {
+    pub base: String,
+    pub start_amount: f64,
+    pub legs: Vec<PlanLeg>,
+    pub final_amount: f64,
+    /// `final_amount - start_amount`, in `base`.
+    pub pnl: f64,
+}
+
+/// Output layout of trade plans.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum PlanFormat {
+    /// One line per leg.
+    Text,
+    /// A single array of plans.
+    Json,
+    /// One table per plan.
+    Markdown,
+}
+
+impl PlanFormat {
+    /// File extension for plans written in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PlanFormat::Text => "txt",
+            PlanFormat::Json => "json",
+            PlanFormat::Markdown => "md",
+        }
+    }
+}
+
+/// Whether node `label` is `asset`, on its own or qualified by an exchange.
+fn is_asset(label: &str, asset: &str) -> bool {
+    label == asset || label.strip_suffix(asset).is_some_and(|prefix| prefix.ends_with(':'))
+}
+
+/// Builds the plan for the cycle traversing `edges`, rotated to start at
+/// `base`, or at the cycle's first currency when no base is given.
+/// Returns `None` when the cycle does not pass through `base`.
+pub fn trade_plan(graph: &ArbGraph, edges: &[EdgeIndex], base: Option<&str>, start_amount: f64) -> Option<TradePlan> {
+    let source = |edge: EdgeIndex| graph.edge_endpoints(edge).map(|(s, _)| graph[s].as_str());
+    let start = match base {
+        Some(base) => edges.iter().position(|&e| source(e).is_some_and(|label| is_asset(label, base)))?,
+        None => 0,
+    };
+
+    let mut balance = start_amount;
+    let mut legs = Vec::with_capacity(edges.len());
+    for (i, &index) in edges[start..].iter().chain(&edges[..start]).enumerate() {
+        let (from, to) = graph.edge_endpoints(index)?;
+        let edge = &graph[index];
+        let balance_before = balance;
+        balance *= edge.rate();
+        legs.push(PlanLeg {
+            leg: i + 1,
+            from: graph[from].clone(),
+            to: graph[to].clone(),
+            symbol: edge.symbol.clone(),
+            side: edge.side,
+            price: edge.raw_price.clone(),
+            rate: edge.rate(),
+            balance_before,
+            balance_after: balance,
+            min_qty: edge.min_qty,
+            max_qty: edge.max_qty,
+            top_qty: edge.top_qty,
+            timestamp: edge.timestamp.clone(),
+        });
+    }
+    Some(TradePlan {
+        base: legs.first()?.from.clone(),
+        start_amount,
+        legs,
+        final_amount: balance,
+        pnl: balance - start_amount,
+    })
+}
+
+impl TradePlan {
+    /// Return on the starting amount, in percent.
+    pub fn pnl_percent(&self) -> f64 {
+        self.pnl / self.start_amount * 100.0
+    }
+
+    /// Console rendering, one line per leg.
+    pub fn to_text(&self) -> String {
+        let mut out = format!("Trade plan from {} {} ({} legs):\n", self.start_amount, self.base, self.legs.len());
+        for leg in &self.legs {
+            let _ = write!(
+                out,
+                "  {}. {} {} @ {} (rate {:.8}): {:.8} {} -> {:.8} {}",
+                leg.leg, leg.side, leg.symbol, leg.price, leg.rate, leg.balance_before, leg.from, leg.balance_after, leg.to
+            );
+            let limits = leg.limits();
+            if limits.is_empty() {
+                out.push('\n');
+            } else {
+                let _ = writeln!(out, " [{}]", limits);
+            }
+        }
+        let _ = writeln!(out, "  P&L: {:+.8} {} ({:+.4}%)", self.pnl, self.base, self.pnl_percent());
+        out
+    }
+
+    /// Markdown table with the P&L underneath.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("### {} {} round trip\n\n", self.start_amount, self.base);
+        out.push_str("| Leg | Side | Symbol | Price | Rate | From | To | Balance | Limits |\n");
+        out.push_str("|---|---|---|---|---|---|---|---|---|\n");
+        for leg in &self.legs {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {:.8} | {} | {} | {:.8} {} | {} |",
+                leg.leg,
+                leg.side,
+                leg.symbol,
+                leg.price,
+                leg.rate,
+                leg.from,
+                leg.to,
+                leg.balance_after,
+                leg.to,
+                leg.limits()
+            );
+        }
+        let _ = writeln!(out, "\n**P&L:** {:+.8} {} ({:+.4}%)", self.pnl, self.base, self.pnl_percent());
+        out
+    }
+}
+
+/// Renders plans in `format`; JSON output is a single array.
+pub fn render_plans(plans: &[TradePlan], format: PlanFormat) -> Result<String, serde_json::Error> {
+    Ok(match format {
+        PlanFormat::Text => plans.iter().map(TradePlan::to_text).collect::<Vec<_>>().join("\n"),
+        PlanFormat::Json => serde_json::to_string_pretty(plans)?,
+        PlanFormat::Markdown => plans.iter().map(TradePlan::to_markdown).collect::<Vec<_>>().join("\n"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange_info::SymbolRules;
+    use crate::graph::fixtures::quote;
+    use crate::graph::pair_edges;
+    use crate::ingest::Quote;
+
+    #[test]
+    fn legs_show_lot_bounds_and_quoted_size_apart() {
+        let quote = Quote {
+            bid_qty: Some("3.1".to_string()),
+            timestamp: Some("t1".to_string()),
+            ..quote("ETHBTC", "0.05", "0.05")
+        };
+        let rules = SymbolRules { min_qty: 0.001, max_qty: 100.0, ..SymbolRules::default() };
+        let (sell, buy) = pair_edges(&quote, 0.05, 0.05, 0.0, Some(&rules));
+        assert_eq!((sell.max_qty, sell.top_qty), (Some(100.0), Some(3.1)));
+
+        let mut graph = ArbGraph::default();
+        let eth = graph.add_node("ETH".to_string());
+        let btc = graph.add_node("BTC".to_string());
+        let edges = [graph.add_edge(eth, btc, sell), graph.add_edge(btc, eth, buy)];
+        let plan = trade_plan(&graph, &edges, None, 1.0).unwrap();
+        assert_eq!(plan.legs[0].limits(), "lot 0.001-100, top 3.1, at t1");
+        assert_eq!(plan.legs[1].limits(), "lot 0.001-100, at t1");
+        assert!(plan.to_text().contains("[lot 0.001-100, top 3.1, at t1]"));
+
+        let json = render_plans(&[plan], PlanFormat::Json).unwrap();
+        assert!(json.contains("\"top_qty\": 3.1"));
+        assert_eq!(json.matches("top_qty").count(), 1);
+    }
+}
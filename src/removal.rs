@@ -0,0 +1,303 @@
+//! Strategies for breaking a detected cycle, and the detect-and-remove loop.
+
+use crate::detect::{canonical_cycle, negative_finder, DetectionMode, SccCycle};
+use crate::exact::exceeds_threshold;
+use crate::export::output_dot_file;
+use crate::graph::{average_out_degree, cycle_profit, ArbGraph};
+use crate::metrics::IterationMetrics;
+use clap::ValueEnum;
+use petgraph::graph::NodeIndex;
+use petgraph::Direction;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+/// Breaks a detected negative cycle so the removal loop can move on.
+pub trait RemovalStrategy {
+    /// Name used on the command line and in metrics.csv.
+    fn name(&self) -> &'static str;
+
+    /// Changes the graph so that `cycle` is no longer negative.
+    fn break_cycle(&self, graph: &mut ArbGraph, cycle: &SccCycle);
+}
+
+/// Removes the third node of the cycle, or the last one for shorter cycles.
+pub struct RemoveThirdNode;
+
+impl RemovalStrategy for RemoveThirdNode {
+    fn name(&self) -> &'static str {
+        "third-node"
+    }
+
+    fn break_cycle(&self, graph: &mut ArbGraph, cycle: &SccCycle) {
+        let node_to_remove = match cycle.nodes[..] {
+            [] => return,
+            [.., last] if cycle.nodes.len() < 3 => last,
+            _ => cycle.nodes[2],
+        };
+        if let Some(label) = graph.remove_node(node_to_remove) {
+            println!("Removed node {} ({}) from the negative cycle.", node_to_remove.index(), label);
+        }
+    }
+}
+
+/// Removes the cycle's lightest edge, i.e. its most profitable leg.
+pub struct RemoveMostProfitableEdge;
+
+impl RemovalStrategy for RemoveMostProfitableEdge {
+    fn name(&self) -> &'static str {
+        "most-profitable-edge"
+    }
+
+    fn break_cycle(&self, graph: &mut ArbGraph, cycle: &SccCycle) {
+        let edge = cycle.edges.iter().copied().min_by(|&a, &b| graph[a].log_weight().total_cmp(&graph[b].log_weight()));
+        if let Some(edge) = edge {
+            let (source, target) = graph.edge_endpoints(edge).unwrap();
+            println!("Removed edge {} -> {} from the negative cycle.", graph[source], graph[target]);
+            graph.remove_edge(edge);
+        }
+    }
+}
+
+/// Removes both directions of the cycle's weakest leg, i.e. the whole trading pair.
+pub struct RemoveWeakestLeg;
+
+impl RemovalStrategy for RemoveWeakestLeg {
+    fn name(&self) -> &'static str {
+        "weakest-leg"
+    }
+
+    fn break_cycle(&self, graph: &mut ArbGraph, cycle: &SccCycle) {
+        let edge = cycle.edges.iter().copied().max_by(|&a, &b| graph[a].log_weight().total_cmp(&graph[b].log_weight()));
+        if let Some(edge) = edge {
+            let (source, target) = graph.edge_endpoints(edge).unwrap();
+            println!("Removed pair {} <-> {} from the negative cycle.", graph[source], graph[target]);
+            graph.retain_edges(|g, e| {
+                let endpoints = g.edge_endpoints(e).unwrap();
+                endpoints != (source, target) && endpoints != (target, source)
+            });
+        }
+    }
+}
+
+/// Removes the cycle node with the most incident edges.
+pub struct RemoveHighestDegreeNode;
+
+impl RemovalStrategy for RemoveHighestDegreeNode {
+    fn name(&self) -> &'static str {
+        "highest-degree-node"
+    }
+
+    fn break_cycle(&self, graph: &mut ArbGraph, cycle: &SccCycle) {
+        let degree = |node: NodeIndex| {
+            graph.edges_directed(node, Direction::Outgoing).count()
+                + graph.edges_directed(node, Direction::Incoming).count()
+        };
+        if let Some(&node) = cycle.nodes.iter().max_by_key(|&&node| degree(node)) {
+            if let Some(label) = graph.remove_node(node) {
+                println!("Removed node {} ({}) from the negative cycle.", node.index(), label);
+            }
+        }
+    }
+}
+
+/// Removes every edge the cycle traverses, keeping its nodes.
+pub struct RemoveWholeCycle;
+
+impl RemovalStrategy for RemoveWholeCycle {
+    fn name(&self) -> &'static str {
+        "whole-cycle"
+    }
+
+    fn break_cycle(&self, graph: &mut ArbGraph, cycle: &SccCycle) {
+        println!("Removed {} edges of the negative cycle.", cycle.edges.len());
+        for &edge in &cycle.edges {
+            graph.remove_edge(edge);
+        }
+    }
+}
+
+/// Leaves the graph's shape intact and marks the cycle's edges as consumed by
+/// giving them infinite weight, so no later search can relax through them.
+pub struct MarkConsumed;
+
+impl RemovalStrategy for MarkConsumed {
+    fn name(&self) -> &'static str {
+        "mark-consumed"
+    }
+
+    fn break_cycle(&self, graph: &mut ArbGraph, cycle: &SccCycle) {
+        println!("Marked {} edges of the negative cycle as consumed.", cycle.edges.len());
+        for &edge in &cycle.edges {
+            graph[edge].consumed = true;
+        }
+    }
+}
+
+/// The available removal strategies, as chosen on the command line.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum StrategyKind {
+    /// Remove the third node of the cycle.
+    ThirdNode,
+    /// Remove the cycle's most profitable edge.
+    MostProfitableEdge,
+    /// Remove both directions of the cycle's weakest leg.
+    WeakestLeg,
+    /// Remove the cycle node with the most incident edges.
+    HighestDegreeNode,
+    /// Remove every edge of the cycle.
+    WholeCycle,
+    /// Keep the cycle's edges but mark them consumed.
+    MarkConsumed,
+}
+
+impl StrategyKind {
+    /// The strategy this kind names.
+    pub fn strategy(self) -> Box<dyn RemovalStrategy> {
+        match self {
+            StrategyKind::ThirdNode => Box::new(RemoveThirdNode),
+            StrategyKind::MostProfitableEdge => Box::new(RemoveMostProfitableEdge),
+            StrategyKind::WeakestLeg => Box::new(RemoveWeakestLeg),
+            StrategyKind::HighestDegreeNode => Box::new(RemoveHighestDegreeNode),
+            StrategyKind::WholeCycle => Box::new(RemoveWholeCycle),
+            StrategyKind::MarkConsumed => Box::new(MarkConsumed),
+        }
+    }
+}
+
+/// What one run of `removal_loop` found.
+pub struct RemovalRun {
+    /// One row per distinct cycle that passed the exact check.
+    pub metrics: Vec<IterationMetrics>,
+    /// Every cycle detected, repeats and discarded ones included.
+    pub detections: usize,
+    /// Detections the exact check discarded.
+    pub discarded: usize,
+}
+
+/// Detects and breaks negative cycles until none returning at least
+/// `min_return_bps` basis points per leg remain, recording metrics.
+/// A cycle detected again, in any rotation, is broken again but counted
+/// against its first detection rather than recorded anew. Cycles whose exact
+/// return from the raw prices does not exceed `1 + epsilon` are broken
+/// without being recorded.
+/// When `dot_dir` is set the graph is written there after every iteration.
+pub fn removal_loop(
+    graph: &mut ArbGraph,
+    mode: DetectionMode,
+    strategy: &dyn RemovalStrategy,
+    dot_dir: Option<&Path>,
+    epsilon: f64,
+    min_return_bps: f64,
+) -> Result<RemovalRun, Box<dyn Error>> {
+    let mut metrics: Vec<IterationMetrics> = Vec::new();
+    // Position in `metrics` of each canonical cycle seen so far.
+    let mut seen: HashMap<Vec<String>, usize> = HashMap::new();
+    let mut iteration = 0;
+    let mut discarded = 0;
+    while let Some(negative_cycle) = negative_finder(graph, mode, min_return_bps) {
+        iteration += 1;
+        let profit = cycle_profit(graph, &negative_cycle.edges);
+        println!("Cycle profit: {}", profit);
+        for &edge in &negative_cycle.edges {
+            println!("  {}", graph[edge]);
+        }
+
+        let verified = match exceeds_threshold(graph, &negative_cycle.edges, epsilon) {
+            Ok(verified) => verified,
+            Err(e) => {
+                println!("Cannot verify cycle exactly: {}", e);
+                false
+            }
+        };
+        let currencies: Vec<String> = negative_cycle.nodes.iter().map(|&n| graph[n].clone()).collect();
+        if !verified {
+            discarded += 1;
+            println!("Discarded: exact return does not exceed 1 + {}.", epsilon);
+        } else {
+            match seen.entry(canonical_cycle(&currencies)) {
+                Entry::Occupied(entry) => {
+                    let first = &mut metrics[*entry.get()];
+                    first.detections += 1;
+                    println!("Same cycle as detection {}, seen {} times.", entry.get(), first.detections);
+                }
+                Entry::Vacant(entry) => {
+                    let centrality = average_out_degree(graph);
+                    println!("Average out-degree: {}", centrality);
+                    entry.insert(metrics.len());
+                    metrics.push(IterationMetrics {
+                        profit,
+                        max_size: None,
+                        rounded_profit: None,
+                        currencies,
+                        legs: negative_cycle.edges.iter().map(|&e| graph[e].clone()).collect(),
+                        detections: 1,
+                        cycle_length: negative_cycle.nodes.len(),
+                        centrality,
+                        scc: negative_cycle.scc,
+                    });
+                }
+            }
+        }
+
+        // Break the cycle with the selected strategy.
+        strategy.break_cycle(graph, &negative_cycle);
+
+        // Save updated DOT file.
+        if let Some(dot_dir) = dot_dir {
+            let dot_filename = format!("graph_updated_{}.dot", iteration);
+            output_dot_file(graph, dot_dir, &dot_filename)?;
+        }
+    }
+    println!("Processing complete. No more negative cycles detected.");
+    println!(
+        "{} distinct cycles in {} detections, {} discarded by the exact check.",
+        metrics.len(),
+        iteration,
+        discarded
+    );
+    Ok(RemovalRun { metrics, detections: iteration, discarded })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::fixtures::graph;
+
+    /// Whether the remaining, unconsumed edges still close `currencies` into a profitable loop.
+    fn still_profitable(graph: &ArbGraph, currencies: &[String]) -> bool {
+        let node = |label: &String| graph.node_indices().find(|&n| &graph[n] == label);
+        let mut product = 1.0;
+        for (from, to) in currencies.iter().zip(currencies.iter().cycle().skip(1)) {
+            let (Some(from), Some(to)) = (node(from), node(to)) else {
+                return false;
+            };
+            let best = graph.edges_connecting(from, to).map(|e| 2f64.powf(-e.weight().log_weight())).fold(0.0, f64::max);
+            product *= best;
+        }
+        product > 1.0
+    }
+
+    #[test]
+    fn every_strategy_breaks_two_and_three_cycles() {
+        for &kind in StrategyKind::value_variants() {
+            let strategy = kind.strategy();
+            let name = strategy.name();
+            assert_eq!(kind.to_possible_value().unwrap().get_name(), name);
+            // A <-> B returns 1.2, C -> D -> E -> C returns 1.21.
+            let edges = [(0, 1, 2.0), (1, 0, 0.6), (2, 3, 1.1), (3, 4, 1.1), (4, 2, 1.0)];
+            let mut g = graph(5, &edges, &[]);
+            let run = removal_loop(&mut g, DetectionMode::PerScc, strategy.as_ref(), None, 0.0, 0.0).unwrap();
+
+            assert!(negative_finder(&g, DetectionMode::PerScc, 0.0).is_none(), "{}", name);
+            let mut lengths: Vec<_> = run.metrics.iter().map(|m| m.cycle_length).collect();
+            lengths.sort();
+            assert_eq!(lengths, [2, 3], "{}", name);
+            assert_eq!(run.detections, 2, "{}", name);
+            for m in &run.metrics {
+                assert!(!still_profitable(&g, &m.currencies), "{}: {:?}", name, m.currencies);
+            }
+        }
+    }
+}
@@ -0,0 +1,14 @@
+//! Random induced subgraphs for experiments on smaller markets.
+
+use crate::graph::ArbGraph;
+use rand::Rng;
+
+/// Creates an induced subgraph by sampling nodes.
+/// Sampled nodes keep their original indices, so the currency map from
+/// `graph_builder` stays valid for the subgraph.
+pub fn sample_subgraph(graph: &ArbGraph, sample_ratio: f64, rng: &mut impl Rng) -> ArbGraph {
+    let mut new_graph = graph.clone();
+    // Removing a node also drops its edges, leaving the induced subgraph.
+    new_graph.retain_nodes(|_, _| rng.gen::<f64>() < sample_ratio);
+    new_graph
+}
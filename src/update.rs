@@ -0,0 +1,182 @@
+//! Keeping a graph current from a stream of price ticks.
+
+use crate::detect::negative_cycle_through;
+use crate::fees::FeeSchedule;
+use crate::exchange_info::RulesMap;
+use crate::graph::{pair_edges, quote_problem, ArbGraph, GraphBuilder, ParallelEdges};
+use crate::ingest::{IngestionReport, Quote, TickerMapping};
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use std::collections::HashMap;
+
+/// An arbitrage graph that is updated in place, one tick at a time.
+pub struct LiveGraph {
+    pub graph: ArbGraph,
+    pub nodes: HashMap<String, NodeIndex>,
+    /// `base -> other` and `other -> base` edges of each symbol.
+    pairs: HashMap<String, (EdgeIndex, EdgeIndex)>,
+    ticker_mapping: TickerMapping,
+    /// Lot size rules by symbol, bounding the edges of every tick.
+    rules: RulesMap,
+    fees: FeeSchedule,
+}
+
+impl LiveGraph {
+    /// Builds the starting graph from a full snapshot.
+    pub fn new(
+        ticker_mapping: TickerMapping,
+        rules: RulesMap,
+        json: Vec<Quote>,
+        fees: FeeSchedule,
+    ) -> (LiveGraph, IngestionReport) {
+        // Keeping every symbol's own pair, a repeated symbol replaces its pair,
+        // so each symbol has exactly the one pair `update_price` overwrites.
+        let mut builder = GraphBuilder::with_rules(ParallelEdges::KeepAll, rules);
+        builder.add_quotes(&ticker_mapping, json, &fees, None);
+        let pairs = std::mem::take(&mut builder.pairs);
+        let rules = std::mem::take(&mut builder.rules);
+        let (graph, nodes, report) = builder.finish();
+        (LiveGraph { graph, nodes, pairs, ticker_mapping, rules, fees }, report)
+    }
+
+    /// Returns the node for `label`, adding it on first use or again after a
+    /// removal strategy deleted it. Stable graphs reuse freed indices, so an
+    /// entry only counts while its index still holds `label`.
+    fn node(&mut self, label: &str) -> NodeIndex {
+        if let Some(&index) = self.nodes.get(label) {
+            if self.graph.node_weight(index).is_some_and(|l| l == label) {
+                return index;
+            }
+        }
+        let index = self.graph.add_node(label.to_string());
+        self.nodes.insert(label.to_string(), index);
+        index
+    }
+
+    /// Applies one tick, overwriting the weights of the symbol's pair of edges
+    /// or adding the pair on the symbol's first valid tick.
+    /// Returns the two edges, or why the tick was rejected; a rejected tick
+    /// leaves the previous weights in place.
+    pub fn update_price(&mut self, quote: &Quote) -> Result<[EdgeIndex; 2], String> {
+        let Some(mapping) = self.ticker_mapping.get(&quote.symbol) else {
+            return Err(format!("{}: unmapped symbol", quote.symbol));
+        };
+        let (bid, ask) = match (quote.bid.parse::<f64>(), quote.ask.parse::<f64>()) {
+            (Ok(bid), Ok(ask)) => (bid, ask),
+            (Err(e), _) | (_, Err(e)) => return Err(format!("{}: unparsable: {}", quote.symbol, e)),
+        };
+        if let Some(reason) = quote_problem(bid, ask) {
+            return Err(format!("{}: {}", quote.symbol, reason));
+        }
+        let (sell, buy) = pair_edges(quote, bid, ask, self.fees.rate(&quote.symbol), self.rules.get(&quote.symbol));
+
+        // Removal strategies may have deleted one of the edges since, and its
+        // index may have been reused by another symbol's edge.
+        let live = |edge: EdgeIndex| self.graph.edge_weight(edge).is_some_and(|e| e.symbol == quote.symbol);
+        let existing = self.pairs.get(&quote.symbol).copied().filter(|&(forward, reverse)| live(forward) && live(reverse));
+        let (forward, reverse) = match existing {
+            Some((forward, reverse)) => {
+                self.graph[forward] = sell;
+                self.graph[reverse] = buy;
+                (forward, reverse)
+            }
+            None => {
+                let (base, other) = (mapping.base.clone(), mapping.other.clone());
+                let node_a = self.node(&base);
+                let node_b = self.node(&other);
+                let forward = self.graph.add_edge(node_a, node_b, sell);
+                let reverse = self.graph.add_edge(node_b, node_a, buy);
+                self.pairs.insert(quote.symbol.clone(), (forward, reverse));
+                (forward, reverse)
+            }
+        };
+        Ok([forward, reverse])
+    }
+
+    /// Applies one tick and checks only the part of the graph it can have made
+    /// negative, returning the edges of any cycle returning at least
+    /// `min_return_bps` basis points per leg.
+    /// Assumes the graph had no such cycle before the tick.
+    pub fn update_and_check(&mut self, quote: &Quote, min_return_bps: f64) -> Result<Option<Vec<EdgeIndex>>, String> {
+        let changed = self.update_price(quote)?;
+        Ok(negative_cycle_through(&self.graph, &changed, min_return_bps))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange_info::SymbolRules;
+    use crate::graph::fixtures::mapping;
+
+    fn quote(symbol: &str, price: &str) -> Quote {
+        crate::graph::fixtures::quote(symbol, price, price)
+    }
+
+    fn live_graph() -> LiveGraph {
+        let quotes = vec![quote("ETHBTC", "0.05"), quote("ETHUSDT", "3000"), quote("BTCUSDT", "60000")];
+        live_graph_with(RulesMap::new(), quotes)
+    }
+
+    fn live_graph_with(rules: RulesMap, quotes: Vec<Quote>) -> LiveGraph {
+        let mapping = mapping(&[("ETHBTC", "ETH", "BTC"), ("ETHUSDT", "ETH", "USDT"), ("BTCUSDT", "BTC", "USDT")]);
+        LiveGraph::new(mapping, rules, quotes, FeeSchedule::default()).0
+    }
+
+    #[test]
+    fn repeated_symbol_in_the_snapshot_keeps_one_pair() {
+        let mut live = live_graph_with(RulesMap::new(), vec![quote("ETHBTC", "0.05"), quote("ETHBTC", "0.05")]);
+        assert_eq!(live.graph.edge_count(), 2);
+
+        assert_eq!(live.update_and_check(&quote("ETHBTC", "0.06"), 0.0), Ok(None));
+        let prices: Vec<_> = live.graph.edge_weights().map(|edge| edge.raw_price.as_str()).collect();
+        assert_eq!(prices, ["0.06", "0.06"]);
+    }
+
+    #[test]
+    fn update_after_node_removal_re_adds_the_node() {
+        let mut live = live_graph();
+        let eth = live.nodes["ETH"];
+        live.graph.remove_node(eth);
+
+        let [forward, reverse] = live.update_price(&quote("ETHBTC", "0.051")).unwrap();
+        let eth = live.nodes["ETH"];
+        assert_eq!(live.graph[eth], "ETH");
+        assert_eq!(live.graph.edge_endpoints(forward), Some((eth, live.nodes["BTC"])));
+        assert_eq!(live.graph.edge_endpoints(reverse), Some((live.nodes["BTC"], eth)));
+
+        live.update_price(&quote("ETHUSDT", "3100")).unwrap();
+        assert_eq!(live.graph.neighbors(eth).count(), 2);
+    }
+
+    #[test]
+    fn reused_node_index_is_not_taken_for_the_removed_label() {
+        let mut live = live_graph();
+        let eth = live.nodes["ETH"];
+        live.graph.remove_node(eth);
+        let reused = live.graph.add_node("SOL".to_string());
+        assert_eq!(reused, eth);
+
+        live.update_price(&quote("ETHBTC", "0.051")).unwrap();
+        assert_ne!(live.nodes["ETH"], reused);
+        assert_eq!(live.graph[reused], "SOL");
+    }
+
+    #[test]
+    fn ticks_carry_symbol_rules_and_timestamps_onto_edges() {
+        let rules = RulesMap::from([(
+            "ETHBTC".to_string(),
+            SymbolRules { min_qty: 0.001, max_qty: 100.0, ..SymbolRules::default() },
+        )]);
+        let mut live = live_graph_with(rules, vec![quote("ETHBTC", "0.05"), quote("BTCUSDT", "60000")]);
+        let tick = Quote { timestamp: Some("1714564800000".to_string()), ..quote("ETHBTC", "0.051") };
+
+        for edge in live.update_price(&tick).unwrap() {
+            assert_eq!(live.graph[edge].min_qty, Some(0.001));
+            assert_eq!(live.graph[edge].max_qty, Some(100.0));
+            assert_eq!(live.graph[edge].top_qty, None);
+            assert_eq!(live.graph[edge].timestamp.as_deref(), Some("1714564800000"));
+        }
+        let [forward, _] = live.update_price(&quote("BTCUSDT", "61000")).unwrap();
+        assert_eq!(live.graph[forward].min_qty, None);
+    }
+}